@@ -1,10 +1,10 @@
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 use itertools::Itertools;
 
 pub struct DayOne {}
 
 impl Problem for DayOne {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         input
             .split('\n')
             .filter(|s| !s.is_empty())
@@ -12,10 +12,10 @@ impl Problem for DayOne {
             .tuple_windows()
             .filter(|(a, b)| b > a)
             .count()
-            .to_string()
+            .into()
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         input
             .split('\n')
             .filter(|s| !s.is_empty())
@@ -25,7 +25,7 @@ impl Problem for DayOne {
             .tuple_windows()
             .filter(|(a, b)| b > a)
             .count()
-            .to_string()
+            .into()
     }
 }
 
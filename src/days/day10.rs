@@ -1,6 +1,6 @@
 use itertools::Itertools;
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 pub struct DayTen {}
 
 impl DayTen {
@@ -66,17 +66,17 @@ impl DayTen {
 }
 
 impl Problem for DayTen {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let mut total_score = 0i32;
         input.split('\n').filter(|l| !l.is_empty()).for_each(|l| {
             if let Some(corr) = Self::corrupted(l) {
                 total_score = total_score.checked_add(Self::get_score(corr)).unwrap();
             }
         });
-        format!("{}", total_score)
+        total_score.into()
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let mut score_vec = input
             .split('\n')
             .filter(|l| !l.is_empty())
@@ -89,7 +89,7 @@ impl Problem for DayTen {
             .collect_vec();
         score_vec.sort();
         let score = score_vec.get(score_vec.len() / 2).unwrap();
-        format!("{}", score)
+        (*score).into()
     }
 }
 
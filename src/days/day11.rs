@@ -1,6 +1,6 @@
 use itertools::Itertools;
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 
 pub struct DayEleven {}
 
@@ -43,7 +43,7 @@ impl DayEleven {
         v
     }
 
-    fn step(a: &mut Vec<Vec<usize>>) -> usize {
+    fn step(a: &mut [Vec<usize>]) -> usize {
         for i in 0..a.len() {
             for j in 0..a[0].len() {
                 a[i][j] += 1;
@@ -60,7 +60,7 @@ impl DayEleven {
                         changed = true;
                         flashed.push((i, j));
                         for (ii, jj) in Self::neighbors(i, j) {
-                            if ii > a.len() - 1 || jj > a.get(0).unwrap().len() - 1 {
+                            if ii > a.len() - 1 || jj > a.first().unwrap().len() - 1 {
                                 continue;
                             }
                             a[ii][jj] += 1;
@@ -79,19 +79,19 @@ impl DayEleven {
 }
 
 impl Problem for DayEleven {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let mut mat = DayEleven::parse(input);
         let res = (0..100).map(|_| Self::step(&mut mat)).sum::<usize>();
-        format!("{}", res)
+        res.into()
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let mut mat = DayEleven::parse(input);
         let mut i = 0;
         let size = mat.len() * mat[0].len();
         while Self::step(&mut mat) != size {
             i += 1;
         }
-        format!("{}", i + 1)
+        (i + 1).into()
     }
 }
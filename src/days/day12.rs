@@ -1,97 +1,302 @@
 use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::hash::Hash;
+use std::path::Path;
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 
 pub struct DayTwelve {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RevisitRule {
+    /// Small caves are visited at most once.
+    Never,
+    /// A single small cave may be visited this many extra times.
+    OneCave(usize),
+    /// Small caves may be revisited, this many extra visits in total.
+    Budget(usize),
+}
+
 #[derive(Debug)]
 struct Graph {
-    edges: Vec<(usize, usize)>,
     labels: Vec<String>,
+    neighbors: Vec<Vec<usize>>,
+    small: Vec<bool>,
+    // Bit of each small cave other than start and end in a visited set.
+    small_index: Vec<Option<usize>>,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+struct Revisits {
+    cave: Option<usize>,
+    used: usize,
+}
+
+impl Revisits {
+    fn allows(&self, cave: usize, rule: RevisitRule) -> bool {
+        match rule {
+            RevisitRule::Never => false,
+            RevisitRule::OneCave(n) => self.cave.is_none_or(|c| c == cave) && self.used < n,
+            RevisitRule::Budget(n) => self.used < n,
+        }
+    }
+
+    fn revisit(&self, cave: usize, rule: RevisitRule) -> Self {
+        Revisits {
+            cave: match rule {
+                RevisitRule::OneCave(_) => Some(cave),
+                _ => None,
+            },
+            used: self.used + 1,
+        }
+    }
+}
+
+// The small caves on a path so far, by their index in `small_index`.
+trait VisitedSet: Clone + Hash + Eq {
+    fn contains(&self, i: usize) -> bool;
+    fn with(&self, i: usize) -> Self;
+}
+
+impl VisitedSet for u128 {
+    fn contains(&self, i: usize) -> bool {
+        self & (1 << i) != 0
+    }
+
+    fn with(&self, i: usize) -> Self {
+        self | (1 << i)
+    }
+}
+
+impl VisitedSet for Vec<bool> {
+    fn contains(&self, i: usize) -> bool {
+        self[i]
+    }
+
+    fn with(&self, i: usize) -> Self {
+        let mut r = self.clone();
+        r[i] = true;
+        r
+    }
 }
 
 impl Graph {
-    fn dfs(&self, origin: usize, visited: &mut Vec<usize>, counter: &mut usize) {
-        if origin == self.labels.iter().position(|s| s == "end").unwrap() {
-            *counter += 1;
-            return;
-        }
-        for &e in self.edges.iter() {
-            if e.1 == origin {
-                if visited.contains(&e.0) && self.labels[e.0] == self.labels[e.0].to_lowercase() {
-                    continue;
+    fn new(labels: Vec<String>, edges: &[(usize, usize)]) -> Self {
+        let mut neighbors = vec![vec![]; labels.len()];
+        for &(a, b) in edges {
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+        let small = labels
+            .iter()
+            .map(|l| *l == l.to_lowercase())
+            .collect::<Vec<_>>();
+        let start = labels.iter().position(|s| s == "start").unwrap();
+        let end = labels.iter().position(|s| s == "end").unwrap();
+        let mut next_index = 0..;
+        let small_index = (0..labels.len())
+            .map(|i| (small[i] && i != start && i != end).then(|| next_index.next().unwrap()))
+            .collect();
+        Graph {
+            small,
+            small_index,
+            start,
+            end,
+            labels,
+            neighbors,
+        }
+    }
+
+    fn paths(&self, rule: RevisitRule) -> Paths<'_> {
+        let mut visits = vec![0; self.labels.len()];
+        visits[self.start] = 1;
+        Paths {
+            graph: self,
+            rule,
+            stack: vec![(self.start, 0)],
+            visits,
+            revisits: Revisits::default(),
+        }
+    }
+
+    // Memoised on the small caves visited, as a bitmask while they fit one.
+    fn count(&self, rule: RevisitRule) -> usize {
+        let smalls = self.small_index.iter().flatten().count();
+        let revisits = Revisits::default();
+        if smalls <= u128::BITS as usize {
+            self.count_from(self.start, 0u128, revisits, rule, &mut HashMap::new())
+        } else {
+            let visited = vec![false; smalls];
+            self.count_from(self.start, visited, revisits, rule, &mut HashMap::new())
+        }
+    }
+
+    fn count_from<V: VisitedSet>(
+        &self,
+        node: usize,
+        visited: V,
+        revisits: Revisits,
+        rule: RevisitRule,
+        memo: &mut HashMap<(usize, V, Revisits), usize>,
+    ) -> usize {
+        if node == self.end {
+            return 1;
+        }
+        if let Some(&n) = memo.get(&(node, visited.clone(), revisits)) {
+            return n;
+        }
+        let mut total = 0;
+        for &next in &self.neighbors[node] {
+            if next == self.start {
+                continue;
+            }
+            total += match self.small_index[next] {
+                None => self.count_from(next, visited.clone(), revisits, rule, memo),
+                Some(i) if !visited.contains(i) => {
+                    self.count_from(next, visited.with(i), revisits, rule, memo)
                 }
-                visited.push(origin);
-                self.dfs(e.0, visited, counter);
-                visited.pop();
-            } else if e.0 == origin {
-                if visited.contains(&e.1) && self.labels[e.1] == self.labels[e.1].to_lowercase() {
-                    continue;
+                Some(_) if revisits.allows(next, rule) => {
+                    let revisits = revisits.revisit(next, rule);
+                    self.count_from(next, visited.clone(), revisits, rule, memo)
                 }
-                visited.push(origin);
-                self.dfs(e.1, visited, counter);
-                visited.pop();
-            }
+                Some(_) => 0,
+            };
         }
+        memo.insert((node, visited, revisits), total);
+        total
     }
-    fn dfs2(
-        &self,
-        origin: usize,
-        visited_amount: &mut HashMap<usize, usize>,
-        counter: &mut usize,
-        path: String,
-    ) {
-        if visited_amount
+
+    fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.neighbors
+            .iter()
+            .enumerate()
+            .flat_map(|(a, ns)| ns.iter().filter(move |&&b| a < b).map(move |&b| (a, b)))
+    }
+
+    fn edge_usage(&self, rule: RevisitRule) -> HashMap<(usize, usize), usize> {
+        let index: HashMap<&str, usize> = self
+            .labels
             .iter()
-            .filter(|(&i, _j)| self.labels[i] == self.labels[i].to_lowercase())
-            .filter(|(_i, &j)| j > 1)
-            .count()
-            > 1
-        {
-            return;
-        }
-        if origin == self.labels.iter().position(|s| s == "end").unwrap() {
-            {
-                *counter += 1;
-                return;
+            .enumerate()
+            .map(|(i, l)| (l.as_str(), i))
+            .collect();
+        let mut usage = HashMap::new();
+        for path in self.paths(rule) {
+            for w in path.windows(2) {
+                let (a, b) = (index[w[0]], index[w[1]]);
+                *usage.entry((a.min(b), a.max(b))).or_insert(0) += 1;
             }
         }
-        for &e in self.edges.iter() {
-            if e.1 == origin {
-                if *visited_amount.get(&e.0).unwrap() > 1usize
-                    && self.labels[e.0] == self.labels[e.0].to_lowercase()
-                {
-                    continue;
-                }
-                if e.0 == self.labels.iter().position(|s| s == "start").unwrap() {
-                    continue;
-                }
-                *visited_amount.get_mut(&origin).unwrap() += 1;
-                self.dfs2(
-                    e.0,
-                    visited_amount,
-                    counter,
-                    format!("{},{}", path, self.labels[e.0]),
-                );
-                *visited_amount.get_mut(&origin).unwrap() -= 1;
-            } else if e.0 == origin {
-                if *visited_amount.get(&e.1).unwrap() > 1usize
-                    && self.labels[e.1] == self.labels[e.1].to_lowercase()
-                {
-                    continue;
-                }
-                if e.1 == self.labels.iter().position(|s| s == "start").unwrap() {
-                    continue;
+        usage
+    }
+
+    /// Graphviz rendering: small caves are ellipses, big caves boxes, and the
+    /// start and end caves are filled double circles. Edges are labelled with
+    /// how many paths go through them when `usage` is given.
+    fn to_dot(&self, usage: Option<&HashMap<(usize, usize), usize>>) -> String {
+        let mut r = "graph caves {\n".to_string();
+        for (i, label) in self.labels.iter().enumerate() {
+            let attrs = if i == self.start {
+                "shape=doublecircle, style=filled, fillcolor=palegreen"
+            } else if i == self.end {
+                "shape=doublecircle, style=filled, fillcolor=lightcoral"
+            } else if self.small[i] {
+                "shape=ellipse"
+            } else {
+                "shape=box, style=bold"
+            };
+            writeln!(r, "    \"{}\" [{}];", label, attrs).unwrap();
+        }
+        let max_usage = usage
+            .and_then(|u| u.values().max().cloned())
+            .unwrap_or(1)
+            .max(1);
+        for (a, b) in self.edges() {
+            write!(r, "    \"{}\" -- \"{}\"", self.labels[a], self.labels[b]).unwrap();
+            if let Some(usage) = usage {
+                let n = usage.get(&(a, b)).cloned().unwrap_or(0);
+                write!(
+                    r,
+                    " [label=\"{}\", penwidth={:.2}]",
+                    n,
+                    1.0 + 4.0 * n as f64 / max_usage as f64
+                )
+                .unwrap();
+            }
+            r.push_str(";\n");
+        }
+        r.push_str("}\n");
+        r
+    }
+}
+
+/// Lazy depth-first enumeration of the paths from `start` to `end`.
+struct Paths<'a> {
+    graph: &'a Graph,
+    rule: RevisitRule,
+    stack: Vec<(usize, usize)>,
+    visits: Vec<usize>,
+    revisits: Revisits,
+}
+
+impl<'a> Paths<'a> {
+    fn can_enter(&self, cave: usize) -> bool {
+        cave != self.graph.start
+            && (!self.graph.small[cave]
+                || self.visits[cave] == 0
+                || self.revisits.allows(cave, self.rule))
+    }
+
+    fn enter(&mut self, cave: usize) {
+        if self.graph.small[cave] && self.visits[cave] > 0 {
+            self.revisits = self.revisits.revisit(cave, self.rule);
+        }
+        self.visits[cave] += 1;
+        self.stack.push((cave, 0));
+    }
+
+    fn leave(&mut self) {
+        let (cave, _) = self.stack.pop().unwrap();
+        self.visits[cave] -= 1;
+        if self.graph.small[cave] && self.visits[cave] > 0 {
+            self.revisits.used -= 1;
+            if self.revisits.used == 0 {
+                self.revisits.cave = None;
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Paths<'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(&(cave, idx)) = self.stack.last() {
+            if cave == self.graph.end {
+                let graph = self.graph;
+                let path = self
+                    .stack
+                    .iter()
+                    .map(|&(c, _)| graph.labels[c].as_str())
+                    .collect();
+                self.leave();
+                return Some(path);
+            }
+            match self.graph.neighbors[cave].get(idx) {
+                Some(&next) => {
+                    self.stack.last_mut().unwrap().1 += 1;
+                    if self.can_enter(next) {
+                        self.enter(next);
+                    }
                 }
-                *visited_amount.get_mut(&origin).unwrap() += 1;
-                self.dfs2(
-                    e.1,
-                    visited_amount,
-                    counter,
-                    format!("{},{}", path, self.labels[e.1]),
-                );
-                *visited_amount.get_mut(&origin).unwrap() -= 1;
+                None => self.leave(),
             }
         }
+        None
     }
 }
 
@@ -120,38 +325,138 @@ impl DayTwelve {
             };
             edges.push((origin_idx, dest_idx));
         }
-        Graph {
-            edges,
-            labels,
+        Graph::new(labels, &edges)
+    }
+
+    pub fn list_paths(
+        input: &str,
+        rule: RevisitRule,
+        limit: Option<usize>,
+    ) -> Result<(), Box<dyn Error>> {
+        let g = Self::read_tunnels(input);
+        for path in g.paths(rule).take(limit.unwrap_or(usize::MAX)) {
+            println!("{}", path.join(","));
         }
+        println!("Total paths: {}", g.count(rule));
+        Ok(())
+    }
+
+    pub fn export_dot(
+        input: &str,
+        output: &Path,
+        usage: Option<RevisitRule>,
+    ) -> Result<(), Box<dyn Error>> {
+        let g = Self::read_tunnels(input);
+        let usage = usage.map(|rule| g.edge_usage(rule));
+        fs::write(output, g.to_dot(usage.as_ref()))?;
+        Ok(())
     }
 }
 
 impl Problem for DayTwelve {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let g = Self::read_tunnels(input);
-        let mut counter = 0;
-        g.dfs(
-            g.labels.iter().position(|s| s == "start").unwrap(),
-            &mut vec![],
-            &mut counter,
-        );
-        format!("{}", counter)
+        g.count(RevisitRule::Never).into()
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let g = Self::read_tunnels(input);
-        let mut counter = 0;
-        let mut visited = HashMap::new();
-        for n in 0..g.labels.len() {
-            visited.insert(n, 0);
-        }
-        g.dfs2(
-            g.labels.iter().position(|s| s == "start").unwrap(),
-            &mut visited,
-            &mut counter,
-            "start".to_string(),
-        );
-        format!("{}", counter)
+        g.count(RevisitRule::OneCave(1)).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SMALL: &str = "start-A
+start-b
+A-c
+A-b
+b-d
+A-end
+b-end
+";
+
+    const LARGER: &str = "fs-end
+he-DX
+fs-he
+start-DX
+pj-DX
+end-zg
+zg-sl
+zg-pj
+pj-he
+RW-he
+fs-DX
+pj-RW
+zg-RW
+start-pj
+he-WI
+zg-he
+pj-fs
+start-RW
+";
+
+    #[test]
+    fn count_paths() {
+        let g = DayTwelve::read_tunnels(SMALL);
+        assert_eq!(g.count(RevisitRule::Never), 10);
+        assert_eq!(g.count(RevisitRule::OneCave(1)), 36);
+        let g = DayTwelve::read_tunnels(LARGER);
+        assert_eq!(g.count(RevisitRule::Never), 226);
+        assert_eq!(g.count(RevisitRule::OneCave(1)), 3509);
+    }
+
+    #[test]
+    fn count_more_small_caves_than_a_bitmask() {
+        // 150 dead ends off a single small cave, only enterable by
+        // revisiting it.
+        let mut input = "start-s\ns-end\n".to_string();
+        for i in 0..150 {
+            writeln!(input, "s-d{}", i).unwrap();
+        }
+        let g = DayTwelve::read_tunnels(&input);
+        assert_eq!(g.count(RevisitRule::Never), 1);
+        assert_eq!(g.count(RevisitRule::OneCave(1)), 151);
+        assert_eq!(g.paths(RevisitRule::OneCave(1)).count(), 151);
+    }
+
+    #[test]
+    fn enumeration_agrees_with_count() {
+        for input in [SMALL, LARGER] {
+            let g = DayTwelve::read_tunnels(input);
+            for rule in [
+                RevisitRule::Never,
+                RevisitRule::OneCave(1),
+                RevisitRule::OneCave(2),
+                RevisitRule::Budget(2),
+            ] {
+                assert_eq!(g.paths(rule).count(), g.count(rule));
+            }
+        }
+    }
+
+    #[test]
+    fn enumerate_small_cave_system() {
+        let g = DayTwelve::read_tunnels(SMALL);
+        let paths = g.paths(RevisitRule::Never).collect::<Vec<_>>();
+        assert!(paths.contains(&vec!["start", "A", "b", "A", "c", "A", "end"]));
+        assert!(paths
+            .iter()
+            .all(|p| p[0] == "start" && p[p.len() - 1] == "end"));
+    }
+
+    #[test]
+    fn dot_export() {
+        let g = DayTwelve::read_tunnels(SMALL);
+        let usage = g.edge_usage(RevisitRule::Never);
+        let dot = g.to_dot(Some(&usage));
+        assert!(dot.starts_with("graph caves {"));
+        assert!(dot.contains("\"A\" [shape=box, style=bold];"));
+        assert!(dot.contains("\"c\" [shape=ellipse];"));
+        assert!(dot.contains("\"start\" [shape=doublecircle"));
+        assert!(dot.contains("\"b\" -- \"d\" [label=\"0\""));
+        assert_eq!(g.edges().count(), 7);
     }
 }
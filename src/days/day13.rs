@@ -1,4 +1,7 @@
-use crate::problem::problemdef::Problem;
+use std::collections::HashSet;
+
+use crate::ocr;
+use crate::problem::problemdef::{Answer, Problem};
 
 pub struct DayThirteen {}
 
@@ -8,9 +11,77 @@ enum Folding {
     X(usize),
 }
 
+#[derive(Debug, Clone)]
+struct Paper {
+    dots: HashSet<(usize, usize)>,
+    width: usize,
+    height: usize,
+}
+
+impl Paper {
+    fn new(dots: HashSet<(usize, usize)>) -> Self {
+        Paper {
+            width: dots.iter().map(|(i, _j)| i + 1).max().unwrap_or(0),
+            height: dots.iter().map(|(_i, j)| j + 1).max().unwrap_or(0),
+            dots,
+        }
+    }
+
+    // Lookup table with the final position of every coordinate once all the
+    // fold lines along one axis have been applied in order. Coordinates that
+    // end up on a fold line have no position.
+    fn compose(lines: &[usize], size: usize) -> Vec<Option<usize>> {
+        (0..size)
+            .map(|mut x| {
+                for &line in lines {
+                    if x == line {
+                        return None;
+                    }
+                    if x > line {
+                        x = (2 * line).checked_sub(x).unwrap()
+                    }
+                }
+                Some(x)
+            })
+            .collect()
+    }
+
+    fn fold(&self, foldings: &[Folding]) -> Paper {
+        let (mut xs, mut ys) = (vec![], vec![]);
+        for f in foldings {
+            match *f {
+                Folding::X(col) => xs.push(col),
+                Folding::Y(row) => ys.push(row),
+            }
+        }
+        let fold_x = Self::compose(&xs, self.width);
+        let fold_y = Self::compose(&ys, self.height);
+        Paper {
+            dots: self
+                .dots
+                .iter()
+                .map(|&(i, j)| (fold_x[i].unwrap(), fold_y[j].unwrap()))
+                .collect(),
+            width: xs.iter().cloned().min().unwrap_or(self.width),
+            height: ys.iter().cloned().min().unwrap_or(self.height),
+        }
+    }
+
+    fn render(&self, on: char, off: char) -> String {
+        let mut grid = vec![off; (self.width + 1) * self.height];
+        for j in 0..self.height {
+            grid[j * (self.width + 1) + self.width] = '\n';
+        }
+        for &(i, j) in &self.dots {
+            grid[j * (self.width + 1) + i] = on;
+        }
+        grid.into_iter().collect()
+    }
+}
+
 impl DayThirteen {
-    fn read_input(input: &str) -> (Vec<(usize, usize)>, Vec<Folding>) {
-        let mut dots = vec![];
+    fn read_input(input: &str) -> (Paper, Vec<Folding>) {
+        let mut dots = HashSet::new();
         let mut foldings = vec![];
 
         for line in input.split('\n').filter(|l| !l.is_empty()) {
@@ -28,66 +99,71 @@ impl DayThirteen {
                 });
             } else {
                 let mut coords = line.split(',');
-                dots.push((
+                dots.insert((
                     coords.next().unwrap().parse().unwrap(),
                     coords.next().unwrap().parse().unwrap(),
                 ));
             }
         }
 
-        (dots, foldings)
+        (Paper::new(dots), foldings)
     }
-
-    fn fold(dots: &mut Vec<(usize, usize)>, fold: Folding) {
-        match fold {
-            Folding::X(col) => {
-                for dot in dots.iter_mut() {
-                    if dot.0 == col {
-                        panic!()
-                    }
-                    if dot.0 > col {
-                        dot.0 = (2 * col).checked_sub(dot.0).unwrap()
-                    }
-                }
-            }
-            Folding::Y(row) => {
-                for dot in dots.iter_mut() {
-                    if dot.1 == row {
-                        panic!()
-                    }
-                    if dot.1 > row {
-                        dot.1 = (2 * row).checked_sub(dot.1).unwrap()
-                    }
-                }
-            }
-        }
-        dots.sort();
-        dots.dedup();
+}
+impl Problem for DayThirteen {
+    fn part_one(&self, input: &str) -> Answer {
+        let (paper, foldings) = Self::read_input(input);
+        paper.fold(&foldings[..1]).dots.len().into()
     }
 
-    fn show_dots(dots: &[(usize, usize)]) -> String {
-        let mut r = "".to_string();
-        for j in 0..=*dots.iter().map(|(_i, j)| j).max().unwrap() {
-            for i in 0..=*dots.iter().map(|(i, _j)| i).max().unwrap() {
-                r = format!("{}{}", r, if dots.contains(&(i, j)) { "#" } else { "." });
+    fn part_two(&self, input: &str) -> Answer {
+        let (paper, foldings) = Self::read_input(input);
+        let paper = paper.fold(&foldings);
+        match ocr::recognize(paper.width, paper.height, |i, j| {
+            paper.dots.contains(&(i, j))
+        }) {
+            Ok(letters) => Answer::Text(letters),
+            Err(e) => {
+                log::warn!("Unknown glyph at letter {}, returning the dots", e.position);
+                Answer::Art(paper.render('#', '.'))
             }
-            r = format!("{}\n", r);
         }
-        r
     }
 }
-impl Problem for DayThirteen {
-    fn part_one(&self, input: &str) -> String {
-        let (mut dots, foldings) = Self::read_input(input);
-        Self::fold(&mut dots, foldings[0]);
-        format!["{}", dots.len()]
-    }
 
-    fn part_two(&self, input: &str) -> String {
-        let (mut dots, foldings) = Self::read_input(input);
-        for fold in foldings {
-            Self::fold(&mut dots, fold)
-        }
-        Self::show_dots(&dots)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "6,10
+0,14
+9,10
+0,3
+10,4
+4,11
+6,0
+6,12
+4,1
+0,13
+10,12
+3,4
+3,0
+8,4
+1,10
+2,14
+8,10
+9,0
+
+fold along y=7
+fold along x=5
+";
+
+    #[test]
+    fn fold_and_render() {
+        let (paper, foldings) = DayThirteen::read_input(EXAMPLE);
+        assert_eq!(paper.fold(&foldings[..1]).dots.len(), 17);
+        assert_eq!(
+            paper.fold(&foldings).render('#', '.'),
+            "#####\n#...#\n#...#\n#...#\n#####\n.....\n.....\n"
+        );
     }
 }
@@ -1,126 +1,274 @@
-use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 extern crate itertools;
 
 use itertools::Itertools;
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
+use crate::recurrence::{self, Matrix};
+use num::{BigUint, Zero};
 
 pub struct DayFourteen {}
 
-impl DayFourteen {
-    fn read_input(input: &str) -> (String, HashMap<String, String>) {
-        let mut lines = input.split('\n').filter(|l| !l.is_empty());
-        let initial = lines.next().unwrap().to_string();
-        let mut rules = HashMap::new();
-        for line in lines {
-            let mut ruleparts = line.split("->");
-            rules.insert(
-                ruleparts.next().unwrap().trim().to_string(),
-                ruleparts.next().unwrap().trim().to_string(),
-            );
+// Elements are numbered in alphabetical order, and pairs of them index
+// dense n by n arrays.
+struct Polymer {
+    elements: Vec<char>,
+    template: Vec<usize>,
+    // The element inserted between each pair, if any.
+    insertions: Vec<Vec<Option<usize>>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CountOverflow {
+    step: usize,
+}
+
+impl fmt::Display for CountOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Element counts overflow on step {}, count modulo a number instead",
+            self.step
+        )
+    }
+}
+
+impl Error for CountOverflow {}
+
+impl Polymer {
+    fn pair_counts(&self) -> Vec<Vec<u128>> {
+        let n = self.elements.len();
+        let mut counts = vec![vec![0; n]; n];
+        for (&a, &b) in self.template.iter().tuple_windows() {
+            counts[a][b] += 1;
         }
-        (initial, rules)
+        counts
     }
 
-    fn step(input: String, rules: &HashMap<String, String>) -> String {
-        let mut r = "".to_string();
-        let mut first = true;
-        for (a, b) in input.chars().tuple_windows() {
-            let curr = format!("{}{}", a, b);
-            if first {
-                first = false;
-                r.push_str(
-                    &(if rules.contains_key(&curr) {
-                        format!("{}{}{}", a, rules.get(&curr).unwrap(), b)
-                    } else {
-                        curr
-                    }),
-                );
-            } else {
-                r.push_str(
-                    &(if rules.contains_key(&curr) {
-                        format!("{}{}", rules.get(&curr).unwrap(), b)
-                    } else {
-                        b.to_string()
-                    }),
-                );
+    // Pair counts after one insertion step, or None if they overflow.
+    fn step(&self, counts: &[Vec<u128>]) -> Option<Vec<Vec<u128>>> {
+        let n = self.elements.len();
+        let mut next = vec![vec![0u128; n]; n];
+        for (a, b) in (0..n).cartesian_product(0..n) {
+            let count = counts[a][b];
+            if count == 0 {
+                continue;
+            }
+            match self.insertions[a][b] {
+                Some(c) => {
+                    next[a][c] = next[a][c].checked_add(count)?;
+                    next[c][b] = next[c][b].checked_add(count)?;
+                }
+                None => next[a][b] = next[a][b].checked_add(count)?,
             }
         }
-        r
+        Some(next)
     }
 
-    fn count_chars(s: String) -> HashMap<char, i32> {
-        s.to_lowercase().chars().fold(HashMap::new(), |mut map, c| {
-            *map.entry(c).or_insert(0) += 1;
-            map
-        })
+    // How often each element occurs, or None if that overflows: once as the
+    // first element of each of its pairs, plus the last element of the
+    // polymer, which never changes.
+    fn histogram(&self, counts: &[Vec<u128>]) -> Option<Vec<u128>> {
+        let mut histogram = counts
+            .iter()
+            .map(|row| row.iter().try_fold(0u128, |acc, &n| acc.checked_add(n)))
+            .collect::<Option<Vec<_>>>()?;
+        let last = &mut histogram[*self.template.last().unwrap()];
+        *last = last.checked_add(1)?;
+        Some(histogram)
     }
-}
 
-impl Problem for DayFourteen {
-    fn part_one(&self, input: &str) -> String {
-        let (initial, rules) = Self::read_input(input);
-        let mut curr = initial;
-        for _i in 0..10 {
-            curr = Self::step(curr, &rules);
+    // The map taking pair counts, indexed a * n + b, one step forward.
+    fn transition(&self) -> Matrix {
+        let n = self.elements.len();
+        let mut m = Matrix::new(n * n);
+        for (a, b) in (0..n).cartesian_product(0..n) {
+            match self.insertions[a][b] {
+                Some(c) => {
+                    m.add(a * n + c, a * n + b, 1);
+                    m.add(c * n + b, a * n + b, 1);
+                }
+                None => m.add(a * n + b, a * n + b, 1),
+            }
         }
-        let cc = Self::count_chars(curr);
-        format!(
-            "{}",
-            cc.values().max().unwrap() - cc.iter().map(|(_k, &v)| v).min().unwrap()
-        )
+        m
     }
+}
 
-    fn part_two(&self, input: &str) -> String {
-        let (initial, rules) = Self::read_input(input);
+impl DayFourteen {
+    fn read_input(input: &str) -> Polymer {
+        let mut lines = input.split('\n').filter(|l| !l.is_empty());
+        let initial = lines.next().unwrap();
+        let rules = lines
+            .map(|line| {
+                let (pair, inserted) = line.split("->").map(|s| s.trim()).collect_tuple().unwrap();
+                (
+                    pair.chars().collect_tuple::<(_, _)>().unwrap(),
+                    inserted.chars().next().unwrap(),
+                )
+            })
+            .collect_vec();
 
-        let pairs = initial
+        let elements = initial
             .chars()
-            .tuple_windows()
-            .map(|(a, b)| format!("{}{}", a, b));
+            .chain(rules.iter().flat_map(|&((a, b), c)| [a, b, c]))
+            .unique()
+            .sorted()
+            .collect_vec();
+        let id = |c: char| elements.binary_search(&c).unwrap();
+        let mut insertions = vec![vec![None; elements.len()]; elements.len()];
+        for &((a, b), c) in &rules {
+            insertions[id(a)][id(b)] = Some(id(c));
+        }
 
-        let mut pairs_occurrences = HashMap::new();
+        Polymer {
+            template: initial.chars().map(id).collect(),
+            insertions,
+            elements,
+        }
+    }
 
-        for p in pairs {
-            *pairs_occurrences.entry(p).or_insert(0) += 1;
+    // Element histograms of the template and of each of the following steps.
+    fn histograms(polymer: &Polymer, steps: usize) -> Result<Vec<Vec<u128>>, CountOverflow> {
+        let mut counts = polymer.pair_counts();
+        let mut histograms = vec![polymer.histogram(&counts).unwrap()];
+        for step in 1..=steps {
+            let overflow = CountOverflow { step };
+            counts = polymer.step(&counts).ok_or(overflow)?;
+            histograms.push(polymer.histogram(&counts).ok_or(overflow)?);
         }
+        Ok(histograms)
+    }
+
+    fn spread(histogram: &[u128]) -> u128 {
+        let present = histogram.iter().filter(|&&n| n > 0);
+        present.clone().max().unwrap() - present.min().unwrap()
+    }
+
+    // How often each element occurs after `steps` insertion steps, exact or
+    // modulo `modulus`.
+    fn element_counts(input: &str, steps: u64, modulus: Option<&BigUint>) -> Vec<(char, BigUint)> {
+        let polymer = Self::read_input(input);
+        let start = polymer
+            .pair_counts()
+            .into_iter()
+            .flatten()
+            .map(BigUint::from)
+            .collect_vec();
+        let counts = recurrence::advance(&polymer.transition(), &start, steps, modulus);
 
-        for _ in 0..40 {
-            let mut pairs_occurrences_inc = HashMap::<String, i128>::new();
-
-            for (k, v) in pairs_occurrences.iter() {
-                if rules.contains_key(k) {
-                    let newpair1 =
-                        format!("{}{}", k.chars().next().unwrap(), rules.get(k).unwrap());
-                    let newpair2 = format!(
-                        "{}{}",
-                        rules.get(k).unwrap(),
-                        k.chars().nth(1).unwrap(),
-                    );
-                    *pairs_occurrences_inc.entry(k.to_string()).or_insert(0) -= *v;
-                    *pairs_occurrences_inc.entry(newpair1).or_insert(0) += *v;
-                    *pairs_occurrences_inc.entry(newpair2).or_insert(0) += *v;
+        let n = polymer.elements.len();
+        let last = *polymer.template.last().unwrap();
+        polymer
+            .elements
+            .iter()
+            .enumerate()
+            .map(|(a, &e)| {
+                let n = counts[a * n..(a + 1) * n].iter().sum::<BigUint>() + (a == last) as u32;
+                match modulus {
+                    Some(m) => (e, n % m),
+                    None => (e, n),
                 }
-            }
+            })
+            .collect()
+    }
 
-            for (k, v) in pairs_occurrences_inc.iter() {
-                *pairs_occurrences.entry(k.to_string()).or_insert(0) += *v;
+    pub fn print_counts(input: &str, steps: u64, modulus: Option<u64>) {
+        let modulus = modulus.map(BigUint::from);
+        let counts = Self::element_counts(input, steps, modulus.as_ref());
+        for (e, n) in &counts {
+            println!("{}: {}", e, n);
+        }
+        match modulus {
+            Some(m) => println!("Counts are modulo {}", m),
+            None => {
+                let present = counts.iter().map(|(_, n)| n).filter(|n| !n.is_zero());
+                let max = present.clone().max().unwrap();
+                let min = present.min().unwrap();
+                println!("Most minus least common: {}", max - min);
             }
         }
+    }
 
-        let mut char_occurrences = HashMap::new();
-
-        for (k, v) in pairs_occurrences.iter() {
-            for c in k.chars() {
-                *char_occurrences.entry(c).or_insert(0) += *v;
-            }
+    pub fn print_histograms(input: &str, steps: usize) -> Result<(), Box<dyn Error>> {
+        let polymer = Self::read_input(input);
+        let histograms = Self::histograms(&polymer, steps)?;
+        println!("Step {}", polymer.elements.iter().join(" "));
+        for (step, histogram) in histograms.iter().enumerate() {
+            println!("{} {}", step, histogram.iter().join(" "));
         }
+        Ok(())
+    }
+}
 
-        format!(
-            "{}",
-            (char_occurrences.values().max().unwrap()
-                - char_occurrences.values().min().unwrap())
-                / 2
-        )
+impl Problem for DayFourteen {
+    fn part_one(&self, input: &str) -> Answer {
+        let polymer = Self::read_input(input);
+        Self::spread(&Self::histograms(&polymer, 10).unwrap()[10]).into()
+    }
+
+    fn part_two(&self, input: &str) -> Answer {
+        let polymer = Self::read_input(input);
+        Self::spread(&Self::histograms(&polymer, 40).unwrap()[40]).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "NNCB
+
+CH -> B
+HH -> N
+CB -> H
+NH -> C
+HB -> C
+HC -> B
+HN -> C
+NN -> C
+BH -> H
+NC -> B
+NB -> B
+BN -> B
+BB -> N
+BC -> B
+CC -> N
+CN -> C
+";
+
+    #[test]
+    fn counts_elements() {
+        assert_eq!(DayFourteen {}.part_one(EXAMPLE), Answer::Integer(1588));
+        assert_eq!(
+            DayFourteen {}.part_two(EXAMPLE),
+            Answer::Integer(2188189693529)
+        );
+        let polymer = DayFourteen::read_input(EXAMPLE);
+        let histograms = DayFourteen::histograms(&polymer, 10).unwrap();
+        assert_eq!(polymer.elements, ['B', 'C', 'H', 'N']);
+        assert_eq!(histograms[0], [1, 1, 0, 2]);
+        assert_eq!(histograms[1], [2, 2, 1, 2]);
+        assert_eq!(histograms[10], [1749, 298, 161, 865]);
+        let counts = DayFourteen::element_counts(EXAMPLE, 10, None);
+        let expected = [('B', 1749u32), ('C', 298), ('H', 161), ('N', 865)];
+        assert_eq!(
+            counts,
+            expected.map(|(e, n)| (e, BigUint::from(n))).to_vec()
+        );
+        // The polymer has 3 * 2^steps + 1 elements.
+        let total = DayFourteen::element_counts(EXAMPLE, 200, None)
+            .into_iter()
+            .map(|(_, n)| n)
+            .sum::<BigUint>();
+        assert_eq!(
+            total,
+            BigUint::from(3u32) * BigUint::from(2u32).pow(200) + 1u32
+        );
+        assert_eq!(
+            DayFourteen::histograms(&polymer, 200).unwrap_err().step,
+            128
+        );
     }
 }
@@ -1,9 +1,20 @@
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 use itertools::Itertools;
 use priority_queue::DoublePriorityQueue;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::Path;
 
 pub struct DayFifteen {}
 
+#[derive(Debug)]
+struct LowestRisk {
+    risk: i128,
+    path: Vec<(usize, usize)>,
+    dists: Vec<Vec<i128>>,
+}
+
 impl DayFifteen {
     fn read_input(input: &str) -> Vec<Vec<i8>> {
         let mut r = vec![];
@@ -18,7 +29,7 @@ impl DayFifteen {
         r
     }
 
-    fn neighbors(i: usize, j: usize, m: &Vec<Vec<i8>>) -> Vec<(usize, usize)> {
+    fn neighbors(i: usize, j: usize, m: &[Vec<i8>]) -> Vec<(usize, usize)> {
         let mut v = vec![(i + 1, j), (i, j + 1)];
         if i.checked_sub(1).is_some() {
             v.push((i.checked_sub(1).unwrap(), j));
@@ -32,7 +43,7 @@ impl DayFifteen {
             .collect_vec()
     }
 
-    fn dijkstra(m: &Vec<Vec<i8>>) -> i128 {
+    fn dijkstra(m: &[Vec<i8>]) -> LowestRisk {
         let mut q = DoublePriorityQueue::new();
 
         let mut dists = vec![vec![1000000000000i128; m[0].len()]; m.len()];
@@ -58,18 +69,22 @@ impl DayFifteen {
             }
         }
 
-        *dists.last().unwrap().last().unwrap()
-    }
-}
+        let end = (m.len() - 1, m[0].len() - 1);
+        let mut path = vec![end];
+        while *path.last().unwrap() != (0, 0) {
+            let (i, j) = *path.last().unwrap();
+            path.push(parents[i][j]);
+        }
+        path.reverse();
 
-impl Problem for DayFifteen {
-    fn part_one(&self, input: &str) -> String {
-        let m = Self::read_input(input);
-        format!("{}", Self::dijkstra(&m))
+        LowestRisk {
+            risk: dists[end.0][end.1],
+            path,
+            dists,
+        }
     }
 
-    fn part_two(&self, input: &str) -> String {
-        let m = Self::read_input(input);
+    fn expand(m: &[Vec<i8>]) -> Vec<Vec<i8>> {
         let mut m2: Vec<Vec<i8>> = vec![vec![0; m[0].len() * 5]; m.len() * 5];
         for i in 0..m.len() * 5 {
             for j in 0..m.len() * 5 {
@@ -81,11 +96,150 @@ impl Problem for DayFifteen {
                 m2[i][j] = candidate;
             }
         }
-        format!("{}", Self::dijkstra(&m2))
+        m2
+    }
+
+    fn render(m: &[Vec<i8>], path: &[(usize, usize)], color: bool) -> String {
+        let mut on_path = vec![vec![false; m[0].len()]; m.len()];
+        for &(i, j) in path {
+            on_path[i][j] = true;
+        }
+        let mut r = String::with_capacity(m.len() * (m[0].len() + 1));
+        for (row, path_row) in m.iter().zip(on_path.iter()) {
+            for (risk, &p) in row.iter().zip(path_row.iter()) {
+                match (p, color) {
+                    (true, true) => r.push_str(&format!("\x1b[1;31m{}\x1b[0m", risk)),
+                    (true, false) => r.push('*'),
+                    (false, _) => r.push_str(&risk.to_string()),
+                }
+            }
+            r.push('\n');
+        }
+        r
+    }
+
+    fn heat(value: f64) -> [u8; 3] {
+        // Blue (cold) to red (hot), passing through green.
+        let v = value.clamp(0.0, 1.0);
+        let (r, g, b) = if v < 0.5 {
+            (0.0, 2.0 * v, 1.0 - 2.0 * v)
+        } else {
+            (2.0 * v - 1.0, 2.0 - 2.0 * v, 0.0)
+        };
+        [(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8]
+    }
+
+    /// Writes a binary PPM with the risk map on the left and the distance
+    /// from the start on the right, the lowest-risk path drawn in white on both.
+    fn write_heatmap(file: &Path, m: &[Vec<i8>], lowest: &LowestRisk) -> std::io::Result<()> {
+        let (height, width) = (m.len(), m[0].len());
+        let max_dist = lowest
+            .dists
+            .iter()
+            .flatten()
+            .max()
+            .cloned()
+            .unwrap_or(1)
+            .max(1);
+        let mut on_path = vec![vec![false; width]; height];
+        for &(i, j) in &lowest.path {
+            on_path[i][j] = true;
+        }
+
+        let mut w = BufWriter::new(File::create(file)?);
+        write!(w, "P6\n{} {}\n255\n", 2 * width + 1, height)?;
+        for ((row, dist_row), path_row) in m.iter().zip(lowest.dists.iter()).zip(on_path.iter()) {
+            for (risk, &p) in row.iter().zip(path_row.iter()) {
+                let pixel = if p {
+                    [255, 255, 255]
+                } else {
+                    Self::heat((risk - 1) as f64 / 8.0)
+                };
+                w.write_all(&pixel)?;
+            }
+            w.write_all(&[0, 0, 0])?;
+            for (dist, &p) in dist_row.iter().zip(path_row.iter()) {
+                let pixel = if p {
+                    [255, 255, 255]
+                } else {
+                    Self::heat(*dist as f64 / max_dist as f64)
+                };
+                w.write_all(&pixel)?;
+            }
+        }
+        w.flush()
+    }
+
+    pub fn show_path(
+        input: &str,
+        full: bool,
+        heatmap: Option<&Path>,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut m = Self::read_input(input);
+        if full {
+            m = Self::expand(&m);
+        }
+        let lowest = Self::dijkstra(&m);
+        print!(
+            "{}",
+            Self::render(&m, &lowest.path, std::io::stdout().is_terminal())
+        );
+        println!("Lowest total risk: {}", lowest.risk);
+        if let Some(file) = heatmap {
+            Self::write_heatmap(file, &m, &lowest)?;
+        }
+        Ok(())
+    }
+}
+
+impl Problem for DayFifteen {
+    fn part_one(&self, input: &str) -> Answer {
+        let m = Self::read_input(input);
+        Self::dijkstra(&m).risk.into()
+    }
+
+    fn part_two(&self, input: &str) -> Answer {
+        let m = Self::read_input(input);
+        Self::dijkstra(&Self::expand(&m)).risk.into()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    
+    use super::*;
+
+    const EXAMPLE: &str = "1163751742
+1381373672
+2136511328
+3694931569
+7463417111
+1319128137
+1359912421
+3125421639
+1293138521
+2311944581
+";
+
+    #[test]
+    fn lowest_risk_path() {
+        let m = DayFifteen::read_input(EXAMPLE);
+        let lowest = DayFifteen::dijkstra(&m);
+        assert_eq!(lowest.risk, 40);
+        assert_eq!(lowest.path.first(), Some(&(0, 0)));
+        assert_eq!(lowest.path.last(), Some(&(9, 9)));
+        assert_eq!(
+            lowest.path[1..]
+                .iter()
+                .map(|&(i, j)| m[i][j] as i128)
+                .sum::<i128>(),
+            40
+        );
+    }
+
+    #[test]
+    fn render_plain() {
+        let m = DayFifteen::read_input("19\n11\n");
+        let lowest = DayFifteen::dijkstra(&m);
+        assert_eq!(DayFifteen::render(&m, &lowest.path, false), "*9\n**\n");
+    }
 }
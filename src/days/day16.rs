@@ -1,6 +1,6 @@
 use std::str::Chars;
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 enum LengthType {
@@ -214,27 +214,24 @@ impl DaySixteen {
 }
 
 impl Problem for DaySixteen {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let input = input.split('\n').find(|l| !l.is_empty()).unwrap();
         let bin_string = Self::convert_to_binary_from_hex(input);
         let mut bin_iter = bin_string.chars();
 
         let data = Self::read_operator(&mut bin_iter);
 
-        format!("{}", Self::add_version_numbers(&data))
+        Self::add_version_numbers(&data).into()
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let input = input.split('\n').find(|l| !l.is_empty()).unwrap();
         let bin_string = Self::convert_to_binary_from_hex(input);
         let mut bin_iter = bin_string.chars();
 
         let data = Self::read_operator(&mut bin_iter);
 
-        format!(
-            "{}",
-            Self::evaluate_operator(&Operator::Operator(Box::new(data)))
-        )
+        Self::evaluate_operator(&Operator::Operator(Box::new(data))).into()
     }
 }
 
@@ -1,4 +1,4 @@
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 use regex::Regex;
 
 pub struct DaySeventeen {}
@@ -16,15 +16,15 @@ impl DaySeventeen {
 }
 
 impl Problem for DaySeventeen {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let y_re = Regex::new(r"y=(-?\d+)..(-?\d+)").unwrap();
         let y_cap = y_re.captures_iter(input).next().unwrap();
         let ydownlim: i32 = y_cap[1].parse().unwrap();
         let h_max = ((ydownlim.abs() as f64 - 1f64) + 0.5).powi(2) / 2f64;
-        format!("{}", h_max.round())
+        (h_max.round() as i64).into()
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let x_re = Regex::new(r"x=(-?\d+)..(-?\d+)").unwrap();
         let y_re = Regex::new(r"y=(-?\d+)..(-?\d+)").unwrap();
         let x_cap = x_re.captures_iter(input).next().unwrap();
@@ -72,7 +72,7 @@ impl Problem for DaySeventeen {
                 }
             }
         }
-        format!("{}", num_velocities)
+        num_velocities.into()
     }
 }
 
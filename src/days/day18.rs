@@ -1,180 +1,196 @@
-use itertools::{iproduct, Itertools};
+use rayon::prelude::*;
 
-use crate::problem::problemdef::Problem;
-use std::{ops::Add, str::FromStr};
+use crate::problem::problemdef::{Answer, Problem};
+use crate::snailfish::{flat::VecTree, tree::Number, Backend, Snailfish};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::error::Error;
 
-#[derive(Debug, Clone)]
-struct VecTree {
-    vals: Vec<u32>,
-    depths: Vec<u32>,
-}
-
-#[derive(Debug, PartialEq, Eq)]
-struct ParseVecTreeError;
-
-impl FromStr for VecTree {
-    type Err = ParseVecTreeError;
+pub struct DayEighteen {}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut t = VecTree {
-            vals: Vec::new(),
-            depths: Vec::new(),
-        };
+impl DayEighteen {
+    fn parse<N: Snailfish>(input: &str) -> Result<Vec<N>, Box<dyn Error>> {
+        input
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.is_empty())
+            .map(|(i, l)| {
+                l.trim()
+                    .parse()
+                    .map_err(|e| format!("Line {}: {}", i + 1, e).into())
+            })
+            .collect()
+    }
 
-        let mut depth = 0;
-        for c in s.chars() {
-            match c {
-                '[' => {
-                    depth += 1;
-                }
-                ',' => (),
-                ']' => {
-                    depth -= 1;
-                }
-                d => {
-                    t.vals.push(d.to_digit(10).unwrap());
-                    t.depths.push(depth - 1);
-                }
+    // The `k` ordered pairs of distinct numbers whose sums have the largest
+    // magnitudes, best first. Pairs are spread over the rayon thread pool,
+    // each worker keeping its own top `k` until they are merged.
+    fn best_pairs<N: Snailfish + Send + Sync>(numbers: &[N], k: usize) -> Vec<(u64, usize, usize)> {
+        let keep = |mut heap: BinaryHeap<Reverse<(u64, usize, usize)>>, entry| {
+            heap.push(Reverse(entry));
+            if heap.len() > k {
+                heap.pop();
             }
-        }
-        Ok(t)
+            heap
+        };
+        (0..numbers.len())
+            .into_par_iter()
+            .flat_map(|i| (0..numbers.len()).into_par_iter().map(move |j| (i, j)))
+            .filter(|(i, j)| i != j)
+            .map(|(i, j)| ((numbers[i].clone() + numbers[j].clone()).magnitude(), i, j))
+            .fold(BinaryHeap::new, keep)
+            .reduce(BinaryHeap::new, |a, b| {
+                b.into_iter().map(|Reverse(e)| e).fold(a, keep)
+            })
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(e)| e)
+            .collect()
     }
-}
-
-impl Add for VecTree {
-    type Output = Self;
 
-    fn add(self, other: Self) -> Self {
-        Self {
-            vals: self.vals.iter().chain(other.vals.iter()).cloned().collect(),
-            depths: self
-                .depths
-                .iter()
-                .chain(other.depths.iter())
-                .cloned()
-                .map(|x| x + 1)
-                .collect(),
+    pub fn print_best_pairs(input: &str, k: usize) -> Result<(), Box<dyn Error>> {
+        let numbers = Self::parse::<VecTree>(input)?;
+        for (magnitude, i, j) in Self::best_pairs(&numbers, k) {
+            println!("{} + {} = {}", numbers[i], numbers[j], magnitude);
         }
+        Ok(())
     }
-}
-
-#[derive(Debug, PartialEq, Eq)]
-struct ExplodeError;
-
-#[derive(Debug, PartialEq, Eq)]
-struct SplitError;
-
-impl VecTree {
-    fn try_explode(&mut self) -> Result<Self, ExplodeError> {
-        for i in 0..self.depths.len() {
-            let depth = self.depths[i];
-            if depth != 4 {
-                continue;
-            }
 
-            if i != 0 {
-                self.vals[i - 1] += self.vals[i];
-            }
-
-            if i + 2 < self.vals.len() {
-                self.vals[i + 2] += self.vals[i + 1];
-            }
-
-            self.vals[i] = 0;
-            self.depths[i] = 3;
-            self.vals.remove(i + 1);
-            self.depths.remove(i + 1);
-
-            return Ok(self.clone());
+    fn show_sum<N: Snailfish>(input: &str) -> Result<(), Box<dyn Error>> {
+        let numbers = Self::parse::<N>(input)?;
+        if numbers.is_empty() {
+            return Err("No snailfish numbers to add".into());
         }
-
-        Err(ExplodeError)
+        let total: N = numbers.into_iter().sum();
+        println!("{}", total);
+        println!("Magnitude: {}", total.magnitude());
+        Ok(())
     }
 
-    fn try_split(&mut self) -> Result<Self, SplitError> {
-        for i in 0..self.vals.len() {
-            let v = self.vals[i];
-            if v < 10 {
-                continue;
-            }
-
-            let (a, b) = if v % 2 == 0 {
-                (v / 2, v / 2)
-            } else {
-                (v / 2, v / 2 + 1)
-            };
-
-            self.vals[i] = a;
-            self.depths[i] += 1;
-            self.vals.insert(i + 1, b);
-            self.depths.insert(i + 1, self.depths[i]);
-
-            return Ok(self.clone());
+    fn show_reduction<N: Snailfish>(a: &str, b: &str) -> Result<(), Box<dyn Error>> {
+        let (a, b) = (a.parse::<N>()?, b.parse::<N>()?);
+        println!("  {}\n+ {}", a, b);
+        let mut sum = a.pair(b);
+        println!("after addition: {}", sum);
+        for action in sum.reduce_traced() {
+            println!(
+                "after {} at {}: {}",
+                action.kind, action.position, action.after
+            );
         }
-
-        Err(SplitError)
+        println!("= {}", sum);
+        println!("Magnitude: {}", sum.magnitude());
+        Ok(())
     }
 
-    fn reduce(&mut self) -> Self {
-        loop {
-            if self.try_explode().is_err() && self.try_split().is_err() {
-                break;
-            }
+    pub fn explain_sum(a: &str, b: &str, backend: Backend) -> Result<(), Box<dyn Error>> {
+        match backend {
+            Backend::Tree => Self::show_reduction::<Number>(a, b),
+            Backend::Flat => Self::show_reduction::<VecTree>(a, b),
         }
-        self.clone()
     }
 
-    fn score(&self) -> u32 {
-        let mut vals = self.vals.clone();
-        let mut depths = self.depths.clone();
-
-        while vals.len() > 1 {
-            for i in 0..depths.len() - 1 {
-                if depths[i] == depths[i + 1] {
-                    vals[i] = 3 * vals[i] + 2 * vals[i + 1];
-                    vals.remove(i + 1);
-                    depths.remove(i + 1);
-
-                    if depths[i] > 0 {
-                        depths[i] -= 1;
-                    }
-
-                    break;
-                }
-            }
+    pub fn print_sum(input: &str, backend: Backend) -> Result<(), Box<dyn Error>> {
+        match backend {
+            Backend::Tree => Self::show_sum::<Number>(input),
+            Backend::Flat => Self::show_sum::<VecTree>(input),
         }
-
-        vals[0]
     }
 }
 
-pub struct DayEighteen {}
-
 impl Problem for DayEighteen {
-    fn part_one(&self, input: &str) -> String {
-        let mut lines = input.lines().filter(|l| !l.is_empty());
-        let first = VecTree::from_str(lines.next().unwrap()).unwrap();
-        lines
-            .map(|l| l.parse::<VecTree>().unwrap())
-            .fold(first, |n, acc| (n + acc).reduce())
-            .score()
-            .to_string()
+    fn part_one(&self, input: &str) -> Answer {
+        Self::parse::<VecTree>(input)
+            .unwrap()
+            .into_iter()
+            .sum::<VecTree>()
+            .magnitude()
+            .into()
     }
 
-    fn part_two(&self, input: &str) -> String {
-        let lines = input.lines().filter(|l| !l.is_empty()).collect_vec();
-        iproduct!(lines.iter().enumerate(), lines.iter().enumerate())
-            .filter(|((i, _), (j, _))| i < j)
-            .map(|((_, x), (_, y))| {
-                (x.parse::<VecTree>().unwrap() + y.parse::<VecTree>().unwrap())
-                    .reduce()
-                    .score()
-            })
-            .max()
-            .unwrap()
-            .to_string()
+    fn part_two(&self, input: &str) -> Answer {
+        let numbers = Self::parse::<VecTree>(input).unwrap();
+        Self::best_pairs(&numbers, 1)[0].0.into()
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use crate::snailfish::ActionKind;
+    use itertools::Itertools;
+
+    const EXAMPLE: &str = "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]
+[[[5,[2,8]],4],[5,[[9,9],0]]]
+[6,[[[6,2],[5,6]],[[7,6],[4,7]]]]
+[[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]
+[[[7,[6,4]],[3,[1,3]]],[[[5,5],1],9]]
+[[6,[[7,3],[3,2]]],[[[3,8],[5,7]],4]]
+[[[[5,4],[7,7]],8],[[8,3],8]]
+[[9,3],[[9,9],[6,[4,9]]]]
+[[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]
+[[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]
+";
+
+    fn homework<N: Snailfish>() -> (String, u64) {
+        let total: N = DayEighteen::parse::<N>(EXAMPLE).unwrap().into_iter().sum();
+        (total.to_string(), total.magnitude())
+    }
+
+    #[test]
+    fn best_pairs() {
+        let numbers = DayEighteen::parse::<Number>(EXAMPLE).unwrap();
+        let best = DayEighteen::best_pairs(&numbers, 3);
+        assert_eq!(best.len(), 3);
+        assert_eq!(best[0], (3993, 8, 0));
+        assert!(best.windows(2).all(|w| w[0].0 >= w[1].0));
+        let all = DayEighteen::best_pairs(&numbers, usize::MAX);
+        assert_eq!(all.len(), 90);
+        assert_eq!(all[..3], best[..]);
+    }
+
+    #[test]
+    fn backends_agree() {
+        let expected = (
+            "[[[[6,6],[7,6]],[[7,7],[7,0]]],[[[7,7],[7,7]],[[7,8],[9,9]]]]".to_string(),
+            4140,
+        );
+        assert_eq!(homework::<Number>(), expected);
+        assert_eq!(homework::<VecTree>(), expected);
+        assert_eq!(DayEighteen {}.part_two(EXAMPLE), Answer::Integer(3993));
+
+        let (mut tree, mut flat) = (
+            "[[[[4,3],4],4],[7,[[8,4],9]]]".parse::<Number>().unwrap(),
+            "[[[[4,3],4],4],[7,[[8,4],9]]]".parse::<VecTree>().unwrap(),
+        );
+        tree = tree.pair("[1,1]".parse().unwrap());
+        flat = flat.pair("[1,1]".parse().unwrap());
+        let trace = tree.reduce_traced();
+        assert_eq!(trace, flat.reduce_traced());
+        assert_eq!(
+            trace
+                .iter()
+                .map(|a| (a.kind, a.position, a.after.as_str()))
+                .collect_vec(),
+            vec![
+                (ActionKind::Explode, 0, "[[[[0,7],4],[7,[[8,4],9]]],[1,1]]"),
+                (ActionKind::Explode, 4, "[[[[0,7],4],[15,[0,13]]],[1,1]]"),
+                (ActionKind::Split, 3, "[[[[0,7],4],[[7,8],[0,13]]],[1,1]]"),
+                (
+                    ActionKind::Split,
+                    6,
+                    "[[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]"
+                ),
+                (ActionKind::Explode, 6, "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]"),
+            ]
+        );
+        assert_eq!(trace[0].before, "[[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]");
+
+        let unreduced = "[[[[[[1,2],3],4],5],6],[17,[[[[8,9],10],11],12]]]";
+        let mut tree: Number = unreduced.parse().unwrap();
+        let mut flat: VecTree = unreduced.parse().unwrap();
+        tree.reduce();
+        flat.reduce();
+        assert_eq!(tree.to_string(), flat.to_string());
+    }
+}
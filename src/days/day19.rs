@@ -1,24 +1,22 @@
-use crate::problem::problemdef::Problem;
-use array_tool::vec::Intersect;
-use itertools::{iproduct, Itertools};
-use num::{traits::Zero, Integer, Signed};
-use std::collections::{HashMap, HashSet};
+use crate::problem::problemdef::{Answer, Problem};
+use itertools::Itertools;
+use num::{traits::Zero, Integer, Signed, ToPrimitive};
+use std::array;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::fs;
 use std::hash::Hash;
-use std::ops::{Add, Neg, Sub};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::path::Path;
 use std::str::FromStr;
 
-const MATCH_INDICATOR: usize = 66usize;
 const MIN_MATCHES: usize = 12usize;
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
-struct Point<T>
+struct Point<T, const N: usize>([T; N])
 where
-    T: Clone + Signed + Integer + Zero + Neg + Copy + Eq + PartialEq + Hash,
-{
-    x: T,
-    y: T,
-    z: T,
-}
+    T: Clone + Signed + Integer + Zero + Neg + Copy + Eq + PartialEq + Hash;
 
 fn abs<T>(x: T) -> T
 where
@@ -31,166 +29,259 @@ where
     }
 }
 
-impl<T> Add for Point<T>
+impl<T, const N: usize> Add for Point<T, N>
 where
     T: Clone + Signed + Integer + Zero + Neg + Copy + Eq + PartialEq + Sub + Hash,
 {
     type Output = Self;
 
     fn add(self, other: Self) -> Self::Output {
-        Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
-            z: self.z + other.z,
-        }
+        Point(array::from_fn(|i| self.0[i] + other.0[i]))
     }
 }
 
-impl<T> Sub for Point<T>
+impl<T, const N: usize> Sub for Point<T, N>
 where
     T: Clone + Signed + Integer + Zero + Neg + Copy + Eq + PartialEq + Sub + Hash,
 {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self::Output {
-        Self {
-            x: self.x - other.x,
-            y: self.y - other.y,
-            z: self.z - other.z,
-        }
+        Point(array::from_fn(|i| self.0[i] - other.0[i]))
     }
 }
 
-impl<T> Point<T>
+impl<T, const N: usize> Point<T, N>
 where
     T: Signed + Integer + Zero + Neg + Copy + Add + Clone + Hash,
 {
-    fn distance(&self, other: &Self) -> T {
-        abs(self.x - other.x) + abs(self.y - other.y) + abs(self.z - other.z)
-    }
-    fn rotate(&self, rot_idx: usize) -> Self {
-        let (x, y, z) = (self.x, self.y, self.z);
-        let (newx, newy, newz) = match rot_idx {
-            0 => [x, y, z],
-            1 => [x, z, -y],
-            2 => [x, -y, -z],
-            3 => [x, -z, y],
-            4 => [y, x, -z],
-            5 => [y, z, x],
-            6 => [y, -x, z],
-            7 => [y, -z, -x],
-            8 => [z, x, y],
-            9 => [z, y, -x],
-            10 => [z, -x, -y],
-            11 => [z, -y, x],
-            12 => [-x, y, -z],
-            13 => [-x, z, y],
-            14 => [-x, -y, z],
-            15 => [-x, -z, -y],
-            16 => [-y, x, z],
-            17 => [-y, z, -x],
-            18 => [-y, -x, -z],
-            19 => [-y, -z, x],
-            20 => [-z, x, -y],
-            21 => [-z, y, x],
-            22 => [-z, -x, y],
-            23 => [-z, -y, -x],
-            _ => unreachable!(),
-        }
-        .iter()
-        .cloned()
-        .collect_tuple()
-        .unwrap();
-
-        Point {
-            x: newx,
-            y: newy,
-            z: newz,
-        }
+    fn origin() -> Self {
+        Point([T::zero(); N])
+    }
+
+    fn manhattan(&self, other: &Self) -> T {
+        (0..N).fold(T::zero(), |acc, i| acc + abs(self.0[i] - other.0[i]))
+    }
+
+    fn euclidean(&self, other: &Self) -> f64
+    where
+        T: ToPrimitive,
+    {
+        (0..N)
+            .map(|i| (self.0[i] - other.0[i]).to_f64().unwrap().powi(2))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+impl<T, const N: usize> fmt::Display for Point<T, N>
+where
+    T: Signed + Integer + Zero + Neg + Copy + Hash + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.iter().join(","))
     }
 }
 
 #[derive(Debug, PartialEq, Eq)]
 struct ParsePointError;
 
-impl<T> FromStr for Point<T>
+impl<T, const N: usize> FromStr for Point<T, N>
 where
     T: Signed + Integer + Zero + Neg + Copy + FromStr + Hash,
 {
     type Err = ParsePointError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (x, y, z) = s.split(',').collect_tuple().ok_or(ParsePointError)?;
+        let coords = s
+            .split(',')
+            .map(|c| c.parse::<T>().map_err(|_| ParsePointError))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Point(coords.try_into().map_err(|_| ParsePointError)?))
+    }
+}
 
-        let x_fromstr = x.parse::<T>().map_err(|_| ParsePointError)?;
-        let y_fromstr = y.parse::<T>().map_err(|_| ParsePointError)?;
-        let z_fromstr = z.parse::<T>().map_err(|_| ParsePointError)?;
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Rotation<T, const N: usize>([[T; N]; N]);
 
-        Ok(Point {
-            x: x_fromstr,
-            y: y_fromstr,
-            z: z_fromstr,
-        })
+impl<T, const N: usize> Rotation<T, N>
+where
+    T: Signed + Integer + Zero + Neg + Copy + Add + Clone + Hash,
+{
+    fn identity() -> Self {
+        Rotation(array::from_fn(|i| {
+            array::from_fn(|j| if i == j { T::one() } else { T::zero() })
+        }))
+    }
+
+    // The rotation group: signed permutation matrices with determinant +1,
+    // 24 of them in three dimensions.
+    fn all() -> Vec<Self> {
+        let mut r = vec![];
+        for perm in (0..N).permutations(N) {
+            let inversions = (0..N)
+                .tuple_combinations()
+                .filter(|&(i, j)| perm[i] > perm[j])
+                .count();
+            for signs in 0..1u32 << N {
+                if !(inversions + signs.count_ones() as usize).is_multiple_of(2) {
+                    continue;
+                }
+                let mut m = [[T::zero(); N]; N];
+                for (row, &col) in perm.iter().enumerate() {
+                    m[row][col] = if signs & (1 << row) != 0 {
+                        -T::one()
+                    } else {
+                        T::one()
+                    };
+                }
+                r.push(Rotation(m));
+            }
+        }
+        r
+    }
+
+    fn apply(&self, p: &Point<T, N>) -> Point<T, N> {
+        Point(array::from_fn(|i| {
+            (0..N).fold(T::zero(), |acc, k| acc + self.0[i][k] * p.0[k])
+        }))
+    }
+}
+
+impl<T, const N: usize> Mul for Rotation<T, N>
+where
+    T: Signed + Integer + Zero + Neg + Copy + Add + Clone + Hash,
+{
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self::Output {
+        Rotation(array::from_fn(|i| {
+            array::from_fn(|j| (0..N).fold(T::zero(), |acc, k| acc + self.0[i][k] * other.0[k][j]))
+        }))
     }
 }
 
+// Every scanner placed in the frame of scanner 0, so that a point `p` seen
+// by scanner `i` is at `rotations[i] * p + positions[i]`.
+#[derive(Debug)]
+struct ScannerMap<T, const N: usize>
+where
+    T: Clone + Signed + Integer + Zero + Neg + Copy + Eq + PartialEq + Hash,
+{
+    positions: Vec<Point<T, N>>,
+    rotations: Vec<Rotation<T, N>>,
+    beacons: HashSet<Point<T, N>>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum CloudFormat {
+    Ply,
+    Xyz,
+    Csv,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct UnconnectedScannersError(Vec<usize>);
+
+impl fmt::Display for UnconnectedScannersError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Scanners {:?} could not be connected to scanner 0",
+            self.0
+        )
+    }
+}
+
+impl Error for UnconnectedScannersError {}
+
+// Beacon pairs `((a, b), (c, d))` with the same fingerprint, where `a` and
+// `b` belong to the first scanner and `c` and `d` to the second one.
+type SharedFingerprints = HashMap<(usize, usize), Vec<((usize, usize), (usize, usize))>>;
+
 pub struct DayNineteen {}
 
 impl DayNineteen {
-    fn calculate_distances<T>(
-        pts: impl IntoIterator<Item = Point<T>, IntoIter = ::std::vec::IntoIter<Point<T>>>,
-    ) -> Vec<T>
+    // Invariant under every orientation: the sorted absolute differences.
+    fn fingerprint<T, const N: usize>(p: &Point<T, N>, q: &Point<T, N>) -> [T; N]
     where
         T: Signed + Integer + Zero + Neg + Copy + Add + Hash,
     {
-        let pts = pts.into_iter().collect::<Vec<_>>();
-        iproduct!(&pts, &pts)
-            .filter(|(p1, p2)| p1 != p2)
-            .map(|(p1, p2)| p1.distance(p2))
-            .collect()
+        let mut key = array::from_fn(|i| abs(p.0[i] - q.0[i]));
+        key.sort();
+        key
     }
 
-    fn matches<T>(
-        distances1: impl IntoIterator<Item = T, IntoIter = ::std::vec::IntoIter<T>>,
-        distances2: impl IntoIterator<Item = T, IntoIter = ::std::vec::IntoIter<T>>,
-    ) -> bool
+    // Indexes every pair of beacons of every scanner by fingerprint, and
+    // returns for each pair of scanners `(i, j)` with `i < j` the beacon
+    // pairs of `i` and `j` that share a fingerprint.
+    fn shared_fingerprints<T, const N: usize>(sensors: &[Vec<Point<T, N>>]) -> SharedFingerprints
     where
-        T: Eq + PartialEq + Clone,
+        T: Signed + Integer + Zero + Neg + Copy + Add + Hash,
     {
-        distances1
-            .into_iter()
-            .collect_vec()
-            .intersect(distances2.into_iter().collect_vec())
-            .len()
-            >= MATCH_INDICATOR
+        let mut index: HashMap<[T; N], Vec<(usize, usize, usize)>> = HashMap::new();
+        for (s, sensor) in sensors.iter().enumerate() {
+            for (a, b) in (0..sensor.len()).tuple_combinations() {
+                index
+                    .entry(Self::fingerprint(&sensor[a], &sensor[b]))
+                    .or_default()
+                    .push((s, a, b));
+            }
+        }
+        let mut shared: SharedFingerprints = HashMap::new();
+        for pairs in index.values() {
+            for (&(s1, a, b), &(s2, c, d)) in pairs.iter().tuple_combinations() {
+                if s1 != s2 {
+                    shared.entry((s1, s2)).or_default().push(((a, b), (c, d)));
+                }
+            }
+        }
+        shared
     }
 
-    fn find_rotation<T>(sensor1: &[Point<T>], sensor2: &[Point<T>]) -> Option<(Point<T>, usize)>
+    // Rotation and offset taking the beacons of `sensor2` into the frame of
+    // `sensor1`. Beacons are matched by how many fingerprints they share, so
+    // only those correspondences are tried for each orientation.
+    fn find_transform<T, const N: usize>(
+        sensor1: &[Point<T, N>],
+        sensor2: &[Point<T, N>],
+        shared: impl Iterator<Item = ((usize, usize), (usize, usize))>,
+        rotations: &[Rotation<T, N>],
+        min_matches: usize,
+    ) -> Option<(Rotation<T, N>, Point<T, N>)>
     where
         T: Signed + Integer + Zero + Neg + Copy + Add + Clone + Eq + PartialEq + Hash,
     {
-        for rot_idx in 0..24 {
-            let rotated_sensor2 = sensor2
-                .iter()
-                .map(|p| p.rotate(rot_idx))
-                .collect::<Vec<_>>();
-
-            let mut m: HashMap<Point<T>, usize> = HashMap::new();
-            for d in iproduct!(sensor1.iter().cloned(), rotated_sensor2.iter())
-                .map(|(p1, p2)| p1 - p2.clone())
-            {
-                *m.entry(d).or_default() += 1;
+        let mut votes: HashMap<(usize, usize), usize> = HashMap::new();
+        for ((a, b), (c, d)) in shared {
+            for pair in [(a, c), (a, d), (b, c), (b, d)] {
+                *votes.entry(pair).or_default() += 1;
+            }
+        }
+        let candidates = votes
+            .into_iter()
+            .filter(|(_, v)| *v + 1 >= min_matches)
+            .map(|(pair, _)| pair)
+            .collect_vec();
+        if candidates.len() < min_matches {
+            return None;
+        }
+        for rotation in rotations {
+            let mut offsets: HashMap<Point<T, N>, usize> = HashMap::new();
+            for &(a, c) in &candidates {
+                *offsets
+                    .entry(sensor1[a].clone() - rotation.apply(&sensor2[c]))
+                    .or_default() += 1;
             }
-            let (most_frequent_distance, num_ocurrences) =
-                m.into_iter().max_by_key(|(_, v)| *v).unwrap();
-            if num_ocurrences >= MIN_MATCHES {
-                return Some((most_frequent_distance, rot_idx));
+            let (offset, n) = offsets.into_iter().max_by_key(|(_, v)| *v).unwrap();
+            if n >= min_matches {
+                return Some((rotation.clone(), offset));
             }
         }
         None
     }
 
-    fn read_input<T>(input: &str) -> Vec<Vec<Point<T>>>
+    fn read_input<T, const N: usize>(input: &str) -> Vec<Vec<Point<T, N>>>
     where
         T: Signed + Integer + Zero + Neg + Copy + FromStr + Hash,
     {
@@ -205,175 +296,365 @@ impl DayNineteen {
                 sensors.push(current_sensor);
                 current_sensor = Vec::new();
             } else {
-                let point = Point::<T>::from_str(line).unwrap();
+                let point = Point::<T, N>::from_str(line).unwrap();
                 current_sensor.push(point);
             }
         }
-        sensors
-    }
-
-    fn dfs(
-        links: &[(usize, usize)],
-        visited: &mut HashSet<usize>,
-        currpath: &mut Vec<usize>,
-        current: usize,
-        destination: usize,
-    ) -> Option<Vec<usize>> {
-        if current == destination {
-            return Some(currpath.clone());
-        }
-        visited.insert(current);
-        for (i, j) in links
-            .iter()
-            .cloned()
-            .filter(|(i, j)| *i == current || *j == current)
-        {
-            // i is the current node, j is the next node
-            let (_i, j) = if i == current { (i, j) } else { (j, i) };
-            if visited.contains(&j) {
-                continue;
-            }
-            currpath.push(j);
-            if let Some(result) = Self::dfs(links, visited, currpath, j, destination) {
-                return Some(result);
-            }
-            currpath.pop();
+        if !current_sensor.is_empty() {
+            sensors.push(current_sensor);
         }
-        None
+        sensors
     }
 
-    fn find_path(links: &[(usize, usize)], i: usize, j: usize) -> Vec<usize> {
-        Self::dfs(links, &mut HashSet::new(), &mut vec![0], i, j).unwrap()
-    }
-}
-
-impl Problem for DayNineteen {
-    fn part_one(&self, input: &str) -> String {
-        let sensors = Self::read_input::<i32>(input);
-        assert_eq!(sensors.len(), 33);
-        let mut rotation_offset = HashMap::new();
-        let mut links = vec![];
-        for i in 0..sensors.len() {
+    fn align<T, const N: usize>(
+        sensors: &[Vec<Point<T, N>>],
+        min_matches: usize,
+    ) -> Result<ScannerMap<T, N>, UnconnectedScannersError>
+    where
+        T: Signed + Integer + Zero + Neg + Copy + Add + Clone + Eq + PartialEq + Hash,
+    {
+        let rotations = Rotation::all();
+        let shared = Self::shared_fingerprints(sensors);
+        let mut placed = vec![None; sensors.len()];
+        placed[0] = Some((Rotation::identity(), Point::origin()));
+        let mut queue = VecDeque::from([0]);
+        while let Some(i) = queue.pop_front() {
+            let (rotation, position): (Rotation<T, N>, Point<T, N>) = placed[i].clone().unwrap();
             for j in 0..sensors.len() {
-                if i == j {
+                if placed[j].is_some() {
                     continue;
                 }
-                let distances1 = Self::calculate_distances(sensors[i].clone());
-                let distances2 = Self::calculate_distances(sensors[j].clone());
-                if Self::matches(distances1, distances2) {
-                    let (offset, rotation) = Self::find_rotation(&sensors[i], &sensors[j]).unwrap();
-                    rotation_offset.insert((i, j), (rotation, offset.clone()));
-                    links.push((i, j));
-                    assert!(
-                        sensors[j]
-                            .iter()
-                            .map(|p| p.rotate(rotation) + offset.clone())
-                            .collect::<Vec<_>>()
-                            .intersect(sensors[i].to_vec())
-                            .len()
-                            >= MIN_MATCHES
-                    );
+                // Beacons seen by both scanners share at least this many fingerprints.
+                let pairs = match shared.get(&(i.min(j), i.max(j))) {
+                    Some(pairs) if pairs.len() >= min_matches * (min_matches - 1) / 2 => pairs,
+                    _ => continue,
+                };
+                let pairs = pairs
+                    .iter()
+                    .map(|&(p, q)| if i < j { (p, q) } else { (q, p) });
+                if let Some((r, offset)) =
+                    Self::find_transform(&sensors[i], &sensors[j], pairs, &rotations, min_matches)
+                {
+                    placed[j] = Some((
+                        rotation.clone() * r,
+                        rotation.apply(&offset) + position.clone(),
+                    ));
+                    queue.push_back(j);
                 }
             }
         }
-        let mut result = HashSet::new();
-        sensors[0].iter().for_each(|p| {
-            result.insert(p.clone());
-        });
-        for (i,sensor) in sensors.iter().enumerate() {
-            let path = Self::find_path(&links, 0, i);
-            let mut new_sensors = sensor.clone();
-            for (ii, jj) in path.iter().rev().tuple_windows() {
-                //
-                let (rotation, offset) = rotation_offset.get(&(*jj, *ii)).unwrap();
-                new_sensors = new_sensors
+
+        let unconnected = (0..sensors.len())
+            .filter(|&i| placed[i].is_none())
+            .collect_vec();
+        if !unconnected.is_empty() {
+            return Err(UnconnectedScannersError(unconnected));
+        }
+
+        let (rotations, positions): (Vec<_>, Vec<_>) = placed.into_iter().flatten().unzip();
+        let beacons = sensors
+            .iter()
+            .zip(rotations.iter().zip(positions.iter()))
+            .flat_map(|(sensor, (rotation, position))| {
+                sensor
                     .iter()
-                    .map(|p| p.rotate(*rotation) + offset.clone())
-                    .collect::<Vec<_>>();
-            }
-            new_sensors.iter().for_each(|p| {
-                result.insert(p.clone());
+                    .map(move |p| rotation.apply(p) + position.clone())
+            })
+            .collect();
+        Ok(ScannerMap {
+            positions,
+            rotations,
+            beacons,
+        })
+    }
+
+    fn farthest_scanners<T, const N: usize, D: PartialOrd>(
+        map: &ScannerMap<T, N>,
+        metric: impl Fn(&Point<T, N>, &Point<T, N>) -> D,
+    ) -> (usize, usize)
+    where
+        T: Signed + Integer + Zero + Neg + Copy + Add + Clone + Hash,
+    {
+        (0..map.positions.len())
+            .tuple_combinations()
+            .max_by(|&(a, b), &(c, d)| {
+                metric(&map.positions[a], &map.positions[b])
+                    .partial_cmp(&metric(&map.positions[c], &map.positions[d]))
+                    .unwrap()
+            })
+            .unwrap_or((0, 0))
+    }
+
+    fn point_cloud(map: &ScannerMap<i32, 3>, format: CloudFormat) -> String {
+        let beacons = map.beacons.iter().map(|p| ("beacon", p, [255, 255, 255]));
+        let scanners = map.positions.iter().map(|p| ("scanner", p, [255, 0, 0]));
+        let points = beacons.chain(scanners).collect_vec();
+        let mut r = match format {
+            CloudFormat::Ply => format!(
+                "ply\nformat ascii 1.0\nelement vertex {}\nproperty int x\nproperty int y\n\
+                 property int z\nproperty uchar red\nproperty uchar green\nproperty uchar blue\n\
+                 end_header\n",
+                points.len()
+            ),
+            CloudFormat::Xyz => String::new(),
+            CloudFormat::Csv => "kind,x,y,z\n".to_string(),
+        };
+        for (kind, p, [red, green, blue]) in points {
+            r.push_str(&match format {
+                CloudFormat::Csv => format!("{},{}\n", kind, p),
+                _ => format!("{} {} {} {}\n", p.0.iter().join(" "), red, green, blue),
             });
         }
+        r
+    }
 
-        format!("{}", result.len())
+    pub fn export_map(input: &str, output: &Path) -> Result<(), Box<dyn Error>> {
+        let format = match output.extension().and_then(|e| e.to_str()) {
+            Some("ply") => CloudFormat::Ply,
+            Some("xyz") => CloudFormat::Xyz,
+            Some("csv") => CloudFormat::Csv,
+            _ => return Err("Unknown point cloud format, use a .ply, .xyz or .csv file".into()),
+        };
+        let map = Self::align(&Self::read_input::<i32, 3>(input), MIN_MATCHES)?;
+        for (i, (position, rotation)) in map.positions.iter().zip(map.rotations.iter()).enumerate()
+        {
+            println!("Scanner {} at {} rotation {:?}", i, position, rotation.0);
+        }
+        let (s1, s2) = Self::farthest_scanners(&map, Point::manhattan);
+        println!(
+            "Farthest scanners: {} and {}, manhattan {}, euclidean {:.1}",
+            s1,
+            s2,
+            map.positions[s1].manhattan(&map.positions[s2]),
+            map.positions[s1].euclidean(&map.positions[s2])
+        );
+        fs::write(output, Self::point_cloud(&map, format))?;
+        Ok(())
     }
-    fn part_two(&self, input: &str) -> String {
-        let sensors = Self::read_input::<i32>(input);
-        assert_eq!(sensors.len(), 33);
-        let mut rotation_offset = HashMap::new();
-        let mut links = vec![];
-        for i in 0..sensors.len() {
-            for j in 0..sensors.len() {
-                if i == j {
-                    continue;
-                }
-                let distances1 = Self::calculate_distances(sensors[i].clone());
-                let distances2 = Self::calculate_distances(sensors[j].clone());
-                if Self::matches(distances1, distances2) {
-                    let (offset, rotation) = Self::find_rotation(&sensors[i], &sensors[j]).unwrap();
-                    rotation_offset.insert((i, j), (rotation, offset.clone()));
-                    links.push((i, j));
-                    assert!(
-                        sensors[j]
-                            .iter()
-                            .map(|p| p.rotate(rotation) + offset.clone())
-                            .collect::<Vec<_>>()
-                            .intersect(sensors[i].to_vec())
-                            .len()
-                            >= MIN_MATCHES
-                    );
-                }
-            }
+}
+
+impl Problem for DayNineteen {
+    fn part_one(&self, input: &str) -> Answer {
+        let sensors = Self::read_input::<i32, 3>(input);
+        match Self::align(&sensors, MIN_MATCHES) {
+            Ok(map) => map.beacons.len().into(),
+            Err(e) => Answer::Unsolved(e.to_string()),
         }
-        let mut ssensors = vec![];
-        for i in 1..sensors.len() {
-            let path = Self::find_path(&links, 0, i);
-            let mut new_sensors = vec![Point { x: 0, y: 0, z: 0 }];
-            for (ii, jj) in path.iter().rev().tuple_windows() {
-                //
-                let (rotation, offset) = rotation_offset.get(&(*jj, *ii)).unwrap();
-                new_sensors = new_sensors
-                    .iter()
-                    .map(|p| p.rotate(*rotation) + offset.clone())
-                    .collect::<Vec<_>>();
+    }
+
+    fn part_two(&self, input: &str) -> Answer {
+        let sensors = Self::read_input::<i32, 3>(input);
+        match Self::align(&sensors, MIN_MATCHES) {
+            Ok(map) => {
+                let (s1, s2) = Self::farthest_scanners(&map, Point::manhattan);
+                map.positions[s1].manhattan(&map.positions[s2]).into()
             }
-            ssensors.push(new_sensors[0].clone());
+            Err(e) => Answer::Unsolved(e.to_string()),
         }
-        ssensors.push(Point { x: 0, y: 0, z: 0 });
-        ssensors
-            .iter()
-            .flat_map(|s1| ssensors.iter().map(|s2| s1.distance(s2)).collect_vec())
-            .max()
-            .unwrap()
-            .to_string()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::iproduct;
 
     #[test]
     fn test_distance() {
-        let a = Point { x: 0, y: 0, z: 1 };
-        let b = Point { x: 1, y: 1, z: 3 };
-        assert_eq!(a.distance(&b), 4);
+        let a = Point([0, 0, 1]);
+        let b = Point([1, 1, 3]);
+        assert_eq!(a.manhattan(&b), 4);
+        assert_eq!(a.euclidean(&b), 2.449489742783178);
+        assert_eq!(Point([0, 0]).euclidean(&Point([3, 4])), 5.0);
     }
 
     #[test]
     fn test_from_str_point_i32() {
         assert_eq!(
-            Point::<i32>::from_str("-3,2,4").unwrap(),
-            Point { x: -3, y: 2, z: 4 }
+            Point::<i32, 3>::from_str("-3,2,4").unwrap(),
+            Point([-3, 2, 4])
         );
         assert_eq!(
-            Point::<i128>::from_str("3,2,4").unwrap(),
-            Point {
-                x: 3_i128,
-                y: 2_i128,
-                z: 4_i128
+            Point::<i128, 3>::from_str("3,2,4").unwrap(),
+            Point([3_i128, 2_i128, 4_i128])
+        );
+        assert_eq!(Point::<i32, 2>::from_str("3,2"), Ok(Point([3, 2])));
+        assert_eq!(Point::<i32, 2>::from_str("3,2,4"), Err(ParsePointError));
+    }
+
+    // Scanners placed at the given positions with the given orientations
+    // (indices into `Rotation::all`), each seeing the beacons of a
+    // pseudo-random cloud within 1000 units along every axis. Also returns
+    // how many beacons are seen by at least one scanner.
+    fn synthetic_input<const N: usize>(
+        scanners: &[([i32; N], usize)],
+        beacons: usize,
+    ) -> (String, usize) {
+        let mut seed = 0x2021u64;
+        let mut rand = |range: i32| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) % (2 * range as u64 + 1)) as i32 - range
+        };
+        let cloud = (0..beacons)
+            .map(|_| {
+                let mut p = [0; N];
+                for (i, c) in p.iter_mut().enumerate() {
+                    *c = if i == 0 {
+                        rand(2500) + 1500
+                    } else {
+                        rand(1000)
+                    };
+                }
+                Point(p)
+            })
+            .collect_vec();
+        let rotations = Rotation::<i32, N>::all();
+        let mut seen = HashSet::new();
+        let mut r = String::new();
+        for (n, &(position, rotation)) in scanners.iter().enumerate() {
+            r.push_str(&format!("--- scanner {} ---\n", n));
+            for b in &cloud {
+                let rel = b.clone() - Point(position);
+                if rel.0.iter().all(|c| c.abs() <= 1000) {
+                    r.push_str(&format!("{}\n", rotations[rotation].apply(&rel)));
+                    seen.insert(b.clone());
+                }
             }
+            r.push('\n');
+        }
+        (r, seen.len())
+    }
+
+    fn farthest<const N: usize>(scanners: &[([i32; N], usize)]) -> i32 {
+        iproduct!(scanners.iter(), scanners.iter())
+            .map(|((p1, _), (p2, _))| Point(*p1).manhattan(&Point(*p2)))
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn any_number_of_scanners() {
+        let scanners = [
+            ([0, 0, 0], 0),
+            ([1000, 100, -50], 7),
+            ([2000, -80, 30], 13),
+            ([3000, 40, 60], 22),
+        ];
+        let (input, beacons) = synthetic_input(&scanners, 150);
+        assert_eq!(DayNineteen {}.part_one(&input), beacons.into());
+        assert_eq!(DayNineteen {}.part_two(&input), farthest(&scanners).into());
+    }
+
+    #[test]
+    fn unconnected_scanners_are_reported() {
+        let scanners = [([0, 0, 0], 0), ([1000, 0, 0], 5), ([3200, 0, 0], 3)];
+        let (input, _) = synthetic_input(&scanners, 150);
+        assert_eq!(
+            DayNineteen {}.part_one(&input),
+            Answer::Unsolved("Scanners [2] could not be connected to scanner 0".to_string())
+        );
+    }
+
+    fn solve<const N: usize>(scanners: &[([i32; N], usize)]) {
+        let (input, beacons) = synthetic_input(scanners, 150);
+        let map =
+            DayNineteen::align(&DayNineteen::read_input::<i32, N>(&input), MIN_MATCHES).unwrap();
+        assert_eq!(map.beacons.len(), beacons);
+        let rotations = Rotation::<i32, N>::all();
+        for (i, &(position, rotation)) in scanners.iter().enumerate() {
+            assert_eq!(map.positions[i], Point(position));
+            // The map rotation undoes the orientation the scanner was given.
+            assert_eq!(
+                map.rotations[i].clone() * rotations[rotation].clone(),
+                Rotation::identity()
+            );
+        }
+        let (s1, s2) = DayNineteen::farthest_scanners(&map, Point::manhattan);
+        assert_eq!(
+            map.positions[s1].manhattan(&map.positions[s2]),
+            farthest(scanners)
+        );
+    }
+
+    #[test]
+    fn two_dimensional_scanners() {
+        solve(&[
+            ([0, 0], 0),
+            ([900, 200], 1),
+            ([1800, -150], 3),
+            ([2700, 0], 2),
+        ]);
+    }
+
+    #[test]
+    fn four_dimensional_scanners() {
+        solve(&[
+            ([0, 0, 0, 0], 0),
+            ([800, 100, -50, 30], 57),
+            ([1600, -80, 30, -20], 130),
+            ([2400, 40, 60, 10], 191),
+        ]);
+    }
+
+    #[test]
+    fn rotation_group() {
+        fn check<const N: usize>(order: usize) {
+            let rotations = Rotation::<i32, N>::all();
+            assert_eq!(rotations.len(), order);
+            assert_eq!(rotations.iter().unique().count(), order);
+            assert!(rotations.contains(&Rotation::identity()));
+            for (a, b) in iproduct!(rotations.iter(), rotations.iter()) {
+                assert!(rotations.contains(&(a.clone() * b.clone())));
+            }
+        }
+        check::<2>(4);
+        check::<3>(24);
+        check::<4>(192);
+    }
+
+    // The previous candidate search: every scanner pair compares the full
+    // lists of distances between its beacons.
+    fn calculate_distances(pts: &[Point<i32, 3>]) -> Vec<i32> {
+        iproduct!(pts, pts)
+            .filter(|(p1, p2)| p1 != p2)
+            .map(|(p1, p2)| p1.manhattan(p2))
+            .collect()
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_candidate_search() {
+        use array_tool::vec::Intersect;
+        use std::time::Instant;
+
+        let sensors = DayNineteen::read_input::<i32, 3>(
+            &std::fs::read_to_string("src/inputs/19.txt").unwrap(),
+        );
+        let threshold = MIN_MATCHES * (MIN_MATCHES - 1) / 2;
+
+        let now = Instant::now();
+        let distances = sensors.iter().map(|s| calculate_distances(s)).collect_vec();
+        let pairwise = (0..sensors.len())
+            .tuple_combinations()
+            .filter(|&(i, j)| distances[i].intersect(distances[j].clone()).len() >= threshold)
+            .collect::<HashSet<_>>();
+        let pairwise_time = now.elapsed();
+
+        let now = Instant::now();
+        let indexed = DayNineteen::shared_fingerprints(&sensors)
+            .into_iter()
+            .filter(|(_, pairs)| pairs.len() >= threshold)
+            .map(|(k, _)| k)
+            .collect::<HashSet<_>>();
+        let indexed_time = now.elapsed();
+
+        println!(
+            "pairwise distance lists: {:?}, fingerprint index: {:?}",
+            pairwise_time, indexed_time
         );
+        assert!(indexed.is_superset(&pairwise));
+        assert!(indexed_time < pairwise_time);
     }
 }
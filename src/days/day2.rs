@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 
 pub struct DayTwo {}
 
@@ -33,7 +33,7 @@ impl FromStr for Instruction {
 }
 
 impl Problem for DayTwo {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let mut x = 0;
         let mut y = 0;
 
@@ -47,10 +47,10 @@ impl Problem for DayTwo {
                 Instruction::Up(q) => y -= q,
             });
 
-        format!("{}", x * y)
+        (x * y).into()
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let mut x = 0;
         let mut y = 0;
         let mut aim = 0;
@@ -68,7 +68,7 @@ impl Problem for DayTwo {
                 Instruction::Up(q) => aim -= q,
             });
 
-        format!("{}", x * y)
+        (x * y).into()
     }
 }
 
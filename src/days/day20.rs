@@ -1,125 +1,356 @@
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 use itertools::Itertools;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
 
 pub struct DayTwenty {}
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
-enum Mode {
-    On,
-    Off,
+// Three rows of three pixels, most significant bit first, with the
+// leftmost column of each row cleared so the window can slide right.
+const WINDOW_MASK: usize = 0b110_110_110;
+
+// One bit per pixel, rows padded to whole words. The image sits inside a
+// margin of background pixels so that it can grow without reallocating:
+// every pixel outside `top..bottom` and `left..right` is background.
+#[derive(Clone, Debug)]
+struct Image {
+    rows: usize,
+    cols: usize,
+    words: usize,
+    bits: Vec<u64>,
+    scratch: Vec<u64>,
+    top: usize,
+    bottom: usize,
+    left: usize,
+    right: usize,
+    background: bool,
 }
 
-impl Mode {
-    fn as_bin(&self) -> char {
-        match self {
-            Self::Off => '0',
-            Self::On => '1',
+impl Image {
+    fn new(pixels: &[Vec<bool>], margin: usize) -> Self {
+        let (height, width) = (pixels.len(), pixels.first().map_or(0, |r| r.len()));
+        let (rows, cols) = (height + 2 * margin, width + 2 * margin);
+        let words = cols.div_ceil(64);
+        let mut img = Image {
+            rows,
+            cols,
+            words,
+            bits: vec![0; rows * words],
+            scratch: vec![0; rows * words],
+            top: margin,
+            bottom: margin + height,
+            left: margin,
+            right: margin + width,
+            background: false,
+        };
+        for (r, row) in pixels.iter().enumerate() {
+            for (c, &lit) in row.iter().enumerate() {
+                Self::set(&mut img.bits, words, margin + r, margin + c, lit);
+            }
+        }
+        img
+    }
+
+    fn get(&self, r: usize, c: usize) -> bool {
+        self.bits[r * self.words + c / 64] >> (c % 64) & 1 == 1
+    }
+
+    fn set(bits: &mut [u64], words: usize, r: usize, c: usize, lit: bool) {
+        let word = &mut bits[r * words + c / 64];
+        *word = *word & !(1 << (c % 64)) | (lit as u64) << (c % 64);
+    }
+
+    // Same image with `margin` more pixels of background on every side.
+    fn grow(&mut self, margin: usize) {
+        let (rows, cols) = (self.rows + 2 * margin, self.cols + 2 * margin);
+        let words = cols.div_ceil(64);
+        let mut bits = vec![if self.background { u64::MAX } else { 0 }; rows * words];
+        for r in self.top..self.bottom {
+            for c in self.left..self.right {
+                Self::set(&mut bits, words, r + margin, c + margin, self.get(r, c));
+            }
+        }
+        *self = Image {
+            rows,
+            cols,
+            words,
+            bits,
+            scratch: vec![0; rows * words],
+            top: self.top + margin,
+            bottom: self.bottom + margin,
+            left: self.left + margin,
+            right: self.right + margin,
+            background: self.background,
+        };
+    }
+
+    // Binary PBM of the whole allocated grid but for the outer two pixels,
+    // which every step may read from. Lit pixels are black.
+    fn pbm(&self) -> Vec<u8> {
+        let (rows, cols) = (self.rows.saturating_sub(4), self.cols.saturating_sub(4));
+        let mut r = format!("P4\n{} {}\n", cols, rows).into_bytes();
+        for row in 2..2 + rows {
+            for chunk in &(2..2 + cols).chunks(8) {
+                let byte = chunk
+                    .enumerate()
+                    .fold(0u8, |acc, (i, c)| acc | (self.get(row, c) as u8) << (7 - i));
+                r.push(byte);
+            }
+        }
+        r
+    }
+
+    // Lit pixels, or None when the infinite background is lit.
+    fn lit(&self) -> Option<usize> {
+        match self.background {
+            true => None,
+            false => Some(self.bits.iter().map(|w| w.count_ones() as usize).sum()),
         }
     }
 }
 
 impl DayTwenty {
-    fn read_input(input: &str) -> (Vec<Mode>, Vec<Vec<Mode>>) {
+    fn read_input(input: &str) -> ([bool; 512], Vec<Vec<bool>>) {
         let mut lines = input.lines().filter(|l| !l.is_empty());
-        let algorithm = lines.next().unwrap();
+        let pixel = |c| match c {
+            '.' => false,
+            '#' => true,
+            _ => unreachable!(),
+        };
+        let algorithm = lines.next().unwrap().chars().map(pixel).collect_vec();
         (
-            algorithm
-                .chars()
-                .map(|c| match c {
-                    '.' => Mode::Off,
-                    '#' => Mode::On,
-                    _ => unreachable!(),
-                })
-                .collect_vec(),
+            algorithm.try_into().unwrap(),
             lines
-                .map(|l| {
-                    l.chars()
-                        .map(|c| match c {
-                            '.' => Mode::Off,
-                            '#' => Mode::On,
-                            _ => unreachable!(),
-                        })
-                        .collect_vec()
-                })
+                .map(|l| l.chars().map(pixel).collect_vec())
                 .collect_vec(),
         )
     }
-    fn enlarge_image(v: &mut Vec<Vec<Mode>>, mode: Mode) {
-        for vv in v.iter_mut() {
-            vv.insert(0, mode);
-            vv.push(mode)
+
+    // Enhances the image in place, one pixel further on every side. Each
+    // row is scanned with a 9-bit window that takes in one new column per
+    // pixel. Every pixel outside the image shares the background, so the
+    // next background is looked up with all nine bits equal to it.
+    fn step(img: &mut Image, alg: &[bool; 512]) {
+        if img.top < 2 || img.left < 2 || img.bottom + 2 > img.rows || img.right + 2 > img.cols {
+            img.grow(img.rows.max(img.cols).max(2));
         }
-        let len = v[0].len();
-        v.insert(0, vec![mode; len]);
-        v.push(vec![mode; len]);
-    }
-    fn try_add(x: usize, dx: i32) -> Option<usize> {
-        if x == 0 && dx < 0 {
-            None
-        } else {
-            Some((x as i32 + dx) as usize)
+        let background = alg[if img.background { 511 } else { 0 }];
+        let mut next = std::mem::take(&mut img.scratch);
+        next.fill(if background { u64::MAX } else { 0 });
+        let (top, bottom, left, right) = (img.top - 1, img.bottom + 1, img.left - 1, img.right + 1);
+        let words = img.words;
+        let bit = |row: &[u64], c: usize| (row[c / 64] >> (c % 64) & 1) as usize;
+        for r in top..bottom {
+            let (above, here, below) = (
+                &img.bits[(r - 1) * words..r * words],
+                &img.bits[r * words..(r + 1) * words],
+                &img.bits[(r + 1) * words..(r + 2) * words],
+            );
+            let column = |c| bit(above, c) << 6 | bit(here, c) << 3 | bit(below, c);
+            let mut index = column(left - 1) << 1 | column(left);
+            // The words holding the next column to read, shifted so that it
+            // is their lowest bit.
+            let load = |c: usize| {
+                let (k, shift) = (c / 64, c % 64);
+                (above[k] >> shift, here[k] >> shift, below[k] >> shift)
+            };
+            let (mut a, mut h, mut b) = load(left + 1);
+            let out = &mut next[r * words..(r + 1) * words];
+            let mut pixels = 0u64;
+            for c in left..right {
+                index = (index << 1) & WINDOW_MASK | ((a & 1) << 6 | (h & 1) << 3 | b & 1) as usize;
+                (a, h, b) = (a >> 1, h >> 1, b >> 1);
+                if (c + 2) % 64 == 0 && (c + 2) / 64 < words {
+                    (a, h, b) = load(c + 2);
+                }
+                pixels |= (alg[index] as u64) << (c % 64);
+                if c % 64 == 63 || c + 1 == right {
+                    let first = if c / 64 == left / 64 { left % 64 } else { 0 };
+                    let mask = (u64::MAX >> (63 - c % 64)) & (u64::MAX << first);
+                    out[c / 64] = out[c / 64] & !mask | pixels;
+                    pixels = 0;
+                }
+            }
         }
+        img.scratch = std::mem::replace(&mut img.bits, next);
+        (img.top, img.bottom, img.left, img.right) = (top, bottom, left, right);
+        img.background = background;
     }
-    fn neighbors_index(img: &[Vec<Mode>], (i, j): (usize, usize), bg_mode: Mode) -> usize {
-        let mut indices: Vec<Mode> = vec![];
-        for di in [-1, 0, 1] {
-            for dj in [-1, 0, 1] {
-                let ii = Self::try_add(i, di);
-                let jj = Self::try_add(j, dj);
-                indices.push(if let (Some(ii), Some(jj)) = (ii, jj) {
-                    match img.get(ii) {
-                        Some(row) => match row.get(jj) {
-                            Some(col) => *col,
-                            None => bg_mode,
-                        },
-                        None => bg_mode,
-                    }
-                } else {
-                    bg_mode
-                })
-            }
+
+    // Lit pixels after `steps` enhancements, or None when the infinite
+    // background is lit.
+    fn enhance(input: &str, steps: usize) -> Option<usize> {
+        let (algorithm, pixels) = Self::read_input(input);
+        let mut img = Image::new(&pixels, steps + 2);
+        for _ in 0..steps {
+            Self::step(&mut img, &algorithm);
         }
-        usize::from_str_radix(&indices.iter().map(|m| m.as_bin()).join(""), 2).unwrap()
-    }
-    fn step(img: &mut Vec<Vec<Mode>>, alg: &[Mode], stepno: usize) -> Vec<Vec<Mode>> {
-        Self::enlarge_image(img, if stepno % 2 == 0 { Mode::Off } else { Mode::On });
-        let mut new_img = vec![vec![Mode::Off; img[0].len()]; img.len()];
-        for i in 0..img.len() {
-            for j in 0..img[0].len() {
-                new_img[i][j] = alg[Self::neighbors_index(
-                    img,
-                    (i, j),
-                    if stepno % 2 == 0 { Mode::Off } else { Mode::On },
-                )];
+        img.lit()
+    }
+
+    // Writes the image before and after every step as PBM frames of the same
+    // size, and prints the lit count of each.
+    pub fn export_frames(input: &str, steps: usize, dir: &Path) -> Result<(), Box<dyn Error>> {
+        let (algorithm, pixels) = Self::read_input(input);
+        let mut img = Image::new(&pixels, steps + 2);
+        fs::create_dir_all(dir)?;
+        for step in 0..=steps {
+            if step > 0 {
+                Self::step(&mut img, &algorithm);
             }
+            fs::write(dir.join(format!("step_{:03}.pbm", step)), img.pbm())?;
+            println!("Step {}: {}", step, Self::format_count(img.lit()));
+        }
+        Ok(())
+    }
+
+    fn format_count(count: Option<usize>) -> Answer {
+        match count {
+            Some(n) => n.into(),
+            None => Answer::Text("Infinitely many pixels are lit".to_string()),
         }
-        new_img
     }
 }
 
 impl Problem for DayTwenty {
-    fn part_one(&self, input: &str) -> String {
-        let (algorithm, mut img) = Self::read_input(input);
-        for i in 0..2 {
-            img = Self::step(&mut img, &algorithm, i);
-        }
-        format!(
-            "{}",
-            img.iter().flatten().filter(|c| **c == Mode::On).count()
-        )
+    fn part_one(&self, input: &str) -> Answer {
+        Self::format_count(Self::enhance(input, 2))
     }
 
-    fn part_two(&self, input: &str) -> String {
-        let (algorithm, mut img) = Self::read_input(input);
-        for i in 0..50 {
-            img = Self::step(&mut img, &algorithm, i);
-        }
-        format!(
-            "{}",
-            img.iter().flatten().filter(|c| **c == Mode::On).count()
-        )
+    fn part_two(&self, input: &str) -> Answer {
+        Self::format_count(Self::enhance(input, 50))
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    const ALGORITHM: &str =
+        "..#.#..#####.#.#.#.###.##.....###.##.#..###.####..#####..#....#..#..##..##\
+#..######.###...####..#..#####..##..#.#####...##.#.#..#.##..#.#......#.###\
+.######.###.####...#.##.##..#..#..#####.....#.#....###..#.##......#.....#.\
+.#..#..##..#...##.######.####.####.#.#...#.......#..#.#.#...####.##.#.....\
+.#..#...##.#.##..#...##.#.##..###.#......#.#.......#.#.#.####.###.##...#..\
+...####.#..#..#.##.#....##..#.####....##...##..#...#......#.#.......#.....\
+..##..####..#...#.#.#...##..#.#..###..#####........#..####......#..#";
+
+    const IMAGE: &str = "#..#.
+#....
+##..#
+..#..
+..###
+";
+
+    // Image padded with unlit pixels, where the margin is never updated. Only
+    // pixels within `steps` of the original image are counted, as errors
+    // from the frozen margin creep inwards by one pixel per step.
+    fn padded_enhance(input: &str, steps: usize) -> usize {
+        let (alg, img) = DayTwenty::read_input(input);
+        let pad = 2 * steps + 2;
+        let size = img.len().max(img[0].len()) + 2 * pad;
+        let mut grid = vec![vec![false; size]; size];
+        for (i, row) in img.iter().enumerate() {
+            for (j, &m) in row.iter().enumerate() {
+                grid[i + pad][j + pad] = m;
+            }
+        }
+        let inner = 1..size - 1;
+        for _ in 0..steps {
+            grid = (0..size)
+                .map(|i| {
+                    (0..size)
+                        .map(|j| {
+                            if !inner.contains(&i) || !inner.contains(&j) {
+                                return false;
+                            }
+                            let index = itertools::iproduct!(i - 1..=i + 1, j - 1..=j + 1)
+                                .fold(0, |acc, (ii, jj)| 2 * acc + grid[ii][jj] as usize);
+                            alg[index]
+                        })
+                        .collect()
+                })
+                .collect();
+        }
+        let window = pad - steps..size - pad + steps;
+        grid[window.clone()]
+            .iter()
+            .flat_map(|row| &row[window.clone()])
+            .filter(|&&lit| lit)
+            .count()
+    }
+
+    #[test]
+    fn unlit_background() {
+        let input = format!("{}\n\n{}", ALGORITHM, IMAGE);
+        assert_eq!(DayTwenty::enhance(&input, 2), Some(35));
+        assert_eq!(DayTwenty::enhance(&input, 50), Some(3351));
+        assert_eq!(padded_enhance(&input, 2), 35);
+    }
+
+    #[test]
+    fn flickering_background() {
+        let algorithm = format!("#{}.", &ALGORITHM[1..511]);
+        let input = format!("{}\n\n{}", algorithm, IMAGE);
+        assert_eq!(DayTwenty::enhance(&input, 1), None);
+        for steps in [2, 4, 6] {
+            assert_eq!(
+                DayTwenty::enhance(&input, steps),
+                Some(padded_enhance(&input, steps))
+            );
+        }
+    }
+
+    #[test]
+    fn grows_past_its_margin() {
+        let input = format!("{}\n\n{}", ALGORITHM, IMAGE);
+        let (algorithm, pixels) = DayTwenty::read_input(&input);
+        let mut img = Image::new(&pixels, 0);
+        for _ in 0..50 {
+            DayTwenty::step(&mut img, &algorithm);
+        }
+        assert_eq!(img.lit(), Some(3351));
+    }
+
+    #[test]
+    fn pbm_frame() {
+        let (_, pixels) = DayTwenty::read_input(&format!("{}\n\n{}", ALGORITHM, IMAGE));
+        let img = Image::new(&pixels, 3);
+        let pbm = img.pbm();
+        assert!(pbm.starts_with(b"P4\n7 7\n"));
+        // One byte per row, the image one pixel in from the left.
+        assert_eq!(
+            pbm[b"P4\n7 7\n".len()..],
+            [
+                0,
+                0b0100_1000,
+                0b0100_0000,
+                0b0110_0100,
+                0b0001_0000,
+                0b0001_1100,
+                0
+            ]
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_many_steps() {
+        use std::time::Instant;
+
+        let input = std::fs::read_to_string("src/inputs/20.txt").unwrap();
+        let now = Instant::now();
+        assert_eq!(DayTwenty::enhance(&input, 50), Some(20210));
+        println!("50 steps: {:?}", now.elapsed());
+        let now = Instant::now();
+        assert_eq!(padded_enhance(&input, 50), 20210);
+        println!("50 steps, unpacked reference: {:?}", now.elapsed());
+
+        let now = Instant::now();
+        let lit = DayTwenty::enhance(&input, 500);
+        let elapsed = now.elapsed();
+        println!("500 steps: {:?}, {:?} lit", elapsed, lit);
+        assert!(elapsed.as_secs_f64() < 2.0);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 use itertools::Itertools;
 
 pub struct DayTwentyOne {}
@@ -18,7 +18,7 @@ impl DayTwentyOne {
 }
 
 impl Problem for DayTwentyOne {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let (mut pos1, mut pos2) = input
             .split('\n')
             .filter(|l| !l.is_empty())
@@ -28,25 +28,25 @@ impl Problem for DayTwentyOne {
         let (mut score1, mut score2) = (0i128, 0i128);
         let dice = (1..=100i128).cycle();
         for (i, t) in dice.tuples::<(_, _, _)>().enumerate() {
-            let roll: i128 = vec![t.0, t.1, t.2].iter().sum();
+            let roll: i128 = [t.0, t.1, t.2].iter().sum();
             if i % 2 == 0 {
                 pos1 = (pos1 + roll) % 10;
                 score1 += pos1 + 1;
                 if score1 >= 1000 {
-                    return format!("{}", (score2) * (i as i128 * 3 + 3));
+                    return ((score2) * (i as i128 * 3 + 3)).into();
                 }
             } else {
                 pos2 = (pos2 + roll) % 10;
                 score2 += pos2 + 1;
                 if score1 >= 1000 {
-                    return format!("{}", (score1) * (i as i128 * 3 + 3));
+                    return ((score1) * (i as i128 * 3 + 3)).into();
                 }
             }
         }
-        String::new()
+        Answer::Unsolved("the dice ran out".to_string())
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let roll_frequency = vec![(3, 1), (4, 3), (5, 6), (6, 7), (7, 6), (8, 3), (9, 1)];
         let ( pos1,  pos2) = input
             .split('\n')
@@ -55,7 +55,7 @@ impl Problem for DayTwentyOne {
             .collect_tuple()
             .unwrap();
         let (w1, w2) = Self::wins(pos1, 21, pos2, 21, &roll_frequency);
-        format!("{}", w1.max(w2))
+        w1.max(w2).into()
     }
 }
 
@@ -1,80 +1,242 @@
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 use itertools::Itertools;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
 pub struct DayTwentyTwo {}
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Signed cuboids added by inclusion-exclusion, merged by position.
+    Cuboids,
+    /// Space split along the step boundaries, one axis at a time.
+    Compression,
+}
+
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 enum ActionType {
     On,
     Off,
 }
 
-impl ActionType {
-    fn opposite(&self) -> Self {
-        match self {
-            Self::On => Self::Off,
-            Self::Off => Self::On,
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+struct Interval {
+    start: i32,
+    end: i32,
+}
+
+const AXES: [&str; 4] = ["x", "y", "z", "w"];
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+struct Cuboid<const N: usize>([Interval; N]);
+
+impl<const N: usize> Cuboid<N> {
+    fn intersect(&self, other: &Self) -> Option<Self> {
+        let mut coords = self.0;
+        for (c, o) in coords.iter_mut().zip(other.0.iter()) {
+            *c = Interval {
+                start: c.start.max(o.start),
+                end: c.end.min(o.end),
+            };
+            if c.start > c.end {
+                return None;
+            }
         }
+        Some(Cuboid(coords))
     }
-    fn multiplicative_factor(&self) -> i128 {
-        match self {
-            Self::On => 1,
-            Self::Off => -1,
-        }
+
+    fn volume(&self) -> i128 {
+        self.0
+            .iter()
+            .map(|c| (c.end - c.start + 1) as i128)
+            .product()
+    }
+
+    fn is_part_1(&self) -> bool {
+        self.0
+            .iter()
+            .flat_map(|cc| [cc.start, cc.end])
+            .all(|c| c.abs() <= 50)
     }
 }
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
-struct Interval {
-    start: i32,
-    end: i32,
+impl<const N: usize> fmt::Display for Cuboid<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let coords = self
+            .0
+            .iter()
+            .zip(AXES)
+            .map(|(c, axis)| format!("{}={}..{}", axis, c.start, c.end));
+        write!(f, "{}", coords.format(","))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ParseCuboidError;
+
+impl fmt::Display for ParseCuboidError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expected a cuboid like x=-10..10,y=0..5,z=3..3")
+    }
 }
 
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
-struct Cube {
+impl Error for ParseCuboidError {}
+
+impl<const N: usize> FromStr for Cuboid<N> {
+    type Err = ParseCuboidError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if N > AXES.len() {
+            return Err(ParseCuboidError);
+        }
+        let parts: [&str; N] = s
+            .split(',')
+            .collect_vec()
+            .try_into()
+            .map_err(|_| ParseCuboidError)?;
+        let mut coords = [Interval { start: 0, end: 0 }; N];
+        for ((coord, part), axis) in coords.iter_mut().zip(parts).zip(AXES) {
+            let (start, end) = part
+                .trim()
+                .strip_prefix(axis)
+                .and_then(|p| p.strip_prefix('='))
+                .ok_or(ParseCuboidError)?
+                .split("..")
+                .map(|n| n.parse().map_err(|_| ParseCuboidError))
+                .collect_tuple()
+                .ok_or(ParseCuboidError)?;
+            *coord = Interval {
+                start: start?,
+                end: end?,
+            };
+        }
+        Ok(Cuboid(coords))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Step<const N: usize> {
     at: ActionType,
-    coords: Vec<Interval>,
+    cuboid: Cuboid<N>,
 }
 
-impl Cube {
-    fn intersect(&self, other: &Cube) -> Option<Cube> {
-        let candidate = Cube {
-            at: other.at.opposite(),
-            coords: vec![
-                Interval {
-                    start: self.coords[0].start.max(other.coords[0].start),
-                    end: self.coords[0].end.min(other.coords[0].end),
-                },
-                Interval {
-                    start: self.coords[1].start.max(other.coords[1].start),
-                    end: self.coords[1].end.min(other.coords[1].end),
-                },
-                Interval {
-                    start: self.coords[2].start.max(other.coords[2].start),
-                    end: self.coords[2].end.min(other.coords[2].end),
-                },
-            ],
-        };
-        if candidate.coords[0].start <= candidate.coords[0].end
-            && candidate.coords[1].start <= candidate.coords[1].end
-            && candidate.coords[2].start <= candidate.coords[2].end
-        {
-            Some(candidate)
-        } else {
-            None
+// The lit cubes as a sum of weighted cuboids. Each step subtracts what it
+// overlaps and adds itself when turning cubes on; cuboids that end up in
+// the same place are merged, and dropped once their weight is zero.
+#[derive(Debug, Default)]
+struct Reactor<const N: usize> {
+    cuboids: HashMap<Cuboid<N>, i64>,
+    lit: i128,
+}
+
+impl<const N: usize> Reactor<N> {
+    // Applies one reboot step and returns how many cubes are lit after it.
+    fn apply(&mut self, step: &Step<N>) -> i128 {
+        let mut update: HashMap<Cuboid<N>, i64> = HashMap::new();
+        for (cuboid, weight) in &self.cuboids {
+            if let Some(overlap) = cuboid.intersect(&step.cuboid) {
+                *update.entry(overlap).or_default() -= weight;
+            }
         }
+        if step.at == ActionType::On {
+            *update.entry(step.cuboid).or_default() += 1;
+        }
+        for (cuboid, weight) in update {
+            self.lit += cuboid.volume() * weight as i128;
+            let w = self.cuboids.entry(cuboid).or_default();
+            *w += weight;
+            if *w == 0 {
+                self.cuboids.remove(&cuboid);
+            }
+        }
+        self.lit
     }
 
-    fn is_part_1(&self) -> bool {
-        self.coords
+    fn lit_in(&self, query: &Cuboid<N>) -> i128 {
+        self.cuboids
             .iter()
-            .flat_map(|cc| vec![cc.start, cc.end])
-            .all(|c| c.abs() <= 50)
+            .filter_map(|(c, &w)| Some(c.intersect(query)?.volume() * w as i128))
+            .sum()
+    }
+
+    fn is_lit(&self, point: [i32; N]) -> bool {
+        self.lit_in(&Cuboid(point.map(|c| Interval { start: c, end: c }))) > 0
+    }
+
+    // The part of the reactor within `query`.
+    fn restrict(&self, query: &Cuboid<N>) -> Self {
+        let mut cuboids = HashMap::new();
+        for (cuboid, weight) in &self.cuboids {
+            if let Some(overlap) = cuboid.intersect(query) {
+                *cuboids.entry(overlap).or_default() += weight;
+            }
+        }
+        cuboids.retain(|_, w| *w != 0);
+        Reactor {
+            lit: self.lit_in(query),
+            cuboids,
+        }
+    }
+
+    // Disjoint cuboids covering exactly the lit cubes. Space is split one
+    // axis at a time at the boundaries of the weighted cuboids, and runs of
+    // lit pieces along the last axis are joined.
+    fn lit_cuboids(&self) -> Vec<Cuboid<N>> {
+        let mut r = vec![];
+        let cuboids = self.cuboids.iter().map(|(c, &w)| (*c, w)).collect_vec();
+        let region = [Interval { start: 0, end: 0 }; N];
+        Self::split(&cuboids, region, 0, &mut r);
+        r
+    }
+
+    fn split(
+        cuboids: &[(Cuboid<N>, i64)],
+        mut region: [Interval; N],
+        axis: usize,
+        r: &mut Vec<Cuboid<N>>,
+    ) {
+        if cuboids.is_empty() {
+            return;
+        }
+        if axis == N {
+            if cuboids.iter().map(|(_, w)| w).sum::<i64>() > 0 {
+                match r.last_mut() {
+                    Some(Cuboid(last))
+                        if last[..N - 1] == region[..N - 1]
+                            && last[N - 1].end + 1 == region[N - 1].start =>
+                    {
+                        last[N - 1].end = region[N - 1].end
+                    }
+                    _ => r.push(Cuboid(region)),
+                }
+            }
+            return;
+        }
+        let bounds = cuboids
+            .iter()
+            .flat_map(|(c, _)| [c.0[axis].start, c.0[axis].end + 1])
+            .sorted()
+            .dedup()
+            .collect_vec();
+        for w in bounds.windows(2) {
+            let covering = cuboids
+                .iter()
+                .filter(|(c, _)| c.0[axis].start <= w[0] && w[0] <= c.0[axis].end)
+                .cloned()
+                .collect_vec();
+            region[axis] = Interval {
+                start: w[0],
+                end: w[1] - 1,
+            };
+            Self::split(&covering, region, axis + 1, r);
+        }
     }
 }
 
 impl DayTwentyTwo {
-    fn parse_input(input: &str) -> Vec<Cube> {
+    fn parse_input<const N: usize>(input: &str) -> Vec<Step<N>> {
         let mut ret = vec![];
         for line in input.split('\n').filter(|l| !l.is_empty()) {
             let mut lineparts = line.split(' ');
@@ -83,78 +245,283 @@ impl DayTwentyTwo {
                 "off" => ActionType::Off,
                 _ => unreachable!(),
             };
-            let mut coords = vec![];
-            for coord in lineparts
-                .next()
-                .unwrap()
-                .split(',')
-                .map(|s| s.trim().replace(['x', 'y', 'z', '='], ""))
-            {
-                let (x, y) = coord
-                    .split("..")
-                    .map(|s| s.parse().unwrap())
-                    .collect_tuple()
-                    .unwrap();
-                coords.push(Interval { start: x, end: y });
-            }
-            ret.push(Cube {
+            ret.push(Step {
                 at,
-                coords,
+                cuboid: lineparts.next().unwrap().parse().unwrap(),
             });
         }
         ret
     }
 
-    fn count(cs: Vec<Cube>) -> i128 {
-        cs.iter()
-            .map(|c| {
-                let mut ret = 1i128;
-                for coord in &c.coords {
-                    ret = ret
-                        .checked_mul((coord.end - coord.start + 1) as i128)
-                        .unwrap();
-                }
-                ret * c.at.multiplicative_factor()
+    // Splits the slab shared by `steps` along `axis` at their boundaries and
+    // recurses into each piece with the steps covering it. Once every axis is
+    // split, the last step covering a piece decides whether it is lit.
+    fn compressed_lit<const N: usize>(steps: &[&Step<N>], axis: usize) -> i128 {
+        if !steps.iter().any(|s| s.at == ActionType::On) {
+            return 0;
+        }
+        if axis == N {
+            return (steps.last().unwrap().at == ActionType::On) as i128;
+        }
+        let bounds = steps
+            .iter()
+            .flat_map(|s| [s.cuboid.0[axis].start, s.cuboid.0[axis].end + 1])
+            .sorted()
+            .dedup()
+            .collect_vec();
+        bounds
+            .windows(2)
+            .map(|w| {
+                let covering = steps
+                    .iter()
+                    .filter(|s| s.cuboid.0[axis].start <= w[0] && w[0] <= s.cuboid.0[axis].end)
+                    .cloned()
+                    .collect_vec();
+                (w[1] - w[0]) as i128 * Self::compressed_lit(&covering, axis + 1)
             })
-            .fold(0, |acc, x| acc.checked_add(x).unwrap())
+            .sum()
     }
 
-    fn solve(c: Vec<Cube>) -> String {
-        let mut previous = vec![];
-        for cube in c {
-            let mut add = if cube.at == ActionType::On {
-                vec![cube.clone()]
-            } else {
-                vec![]
+    fn solve<const N: usize>(steps: &[Step<N>], backend: Backend) -> i128 {
+        match backend {
+            Backend::Cuboids => {
+                let mut reactor = Reactor::default();
+                steps
+                    .iter()
+                    .map(|step| reactor.apply(step))
+                    .last()
+                    .unwrap_or(0)
+            }
+            Backend::Compression => Self::compressed_lit(&steps.iter().collect_vec(), 0),
+        }
+    }
+
+    fn initialization_steps(input: &str, initialization: bool) -> Vec<Step<3>> {
+        Self::parse_input(input)
+            .into_iter()
+            .filter(|s| !initialization || s.cuboid.is_part_1())
+            .collect()
+    }
+
+    pub fn reboot(input: &str, backend: Backend, initialization: bool) -> i128 {
+        Self::solve(&Self::initialization_steps(input, initialization), backend)
+    }
+
+    // Prints the lit count after every step, feeding the steps to the
+    // reactor one at a time.
+    pub fn reboot_steps(input: &str, initialization: bool) {
+        let mut reactor = Reactor::default();
+        for step in Self::initialization_steps(input, initialization) {
+            let action = match step.at {
+                ActionType::On => "on",
+                ActionType::Off => "off",
             };
-            for p in &previous {
-                if let Some(intersection) = cube.intersect(p) {
-                    add.push(intersection);
-                }
+            let lit = reactor.apply(&step);
+            println!("{} {}: {}", action, step.cuboid, lit);
+        }
+    }
+
+    pub fn query(
+        input: &str,
+        region: &str,
+        point: Option<&str>,
+        list: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let region: Cuboid<3> = region.parse()?;
+        let point: Option<[i32; 3]> = match point {
+            Some(p) => Some(
+                p.split(',')
+                    .map(|c| c.trim().parse::<i32>())
+                    .collect::<Result<Vec<_>, _>>()?
+                    .try_into()
+                    .map_err(|_| "Expected a point like 10,-3,5")?,
+            ),
+            None => None,
+        };
+        let mut reactor = Reactor::default();
+        for step in Self::parse_input(input) {
+            reactor.apply(&step);
+        }
+        println!("{} cubes lit in {}", reactor.lit_in(&region), region);
+        if let Some(point) = point {
+            println!(
+                "{},{},{} is {}",
+                point[0],
+                point[1],
+                point[2],
+                if reactor.is_lit(point) { "on" } else { "off" }
+            );
+        }
+        if list {
+            for cuboid in reactor.restrict(&region).lit_cuboids() {
+                println!("{}", cuboid);
             }
-            previous.append(&mut add);
         }
-        let res = Self::count(previous);
-        format!("{res}")
+        Ok(())
     }
 }
 
 impl Problem for DayTwentyTwo {
-    fn part_one(&self, input: &str) -> String {
-        let cubes = Self::parse_input(input)
-            .iter()
-            .filter(|&c| c.is_part_1())
-            .cloned()
-            .collect();
-        Self::solve(cubes)
+    fn part_one(&self, input: &str) -> Answer {
+        Self::reboot(input, Backend::Cuboids, true).into()
     }
 
-    fn part_two(&self, input: &str) -> String {
-        Self::solve(Self::parse_input(input))
+    fn part_two(&self, input: &str) -> Answer {
+        Self::reboot(input, Backend::Cuboids, false).into()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    
+    use super::*;
+    use std::collections::HashSet;
+
+    const EXAMPLE: &str = "on x=10..12,y=10..12,z=10..12
+on x=11..13,y=11..13,z=11..13
+off x=9..11,y=9..11,z=9..11
+on x=10..10,y=10..10,z=10..10
+";
+
+    #[test]
+    fn backends_agree() {
+        for backend in [Backend::Cuboids, Backend::Compression] {
+            assert_eq!(DayTwentyTwo::reboot(EXAMPLE, backend, true), 39);
+        }
+        let input = std::fs::read_to_string("src/inputs/22.txt").unwrap();
+        let steps = DayTwentyTwo::parse_input::<3>(&input);
+        assert_eq!(
+            DayTwentyTwo::solve(&steps[..60], Backend::Cuboids),
+            DayTwentyTwo::solve(&steps[..60], Backend::Compression)
+        );
+    }
+
+    #[test]
+    fn region_queries() {
+        let mut reactor = Reactor::default();
+        for step in DayTwentyTwo::parse_input(EXAMPLE) {
+            reactor.apply(&step);
+        }
+        let region: Cuboid<3> = "x=10..11,y=10..11,z=10..11".parse().unwrap();
+        assert_eq!(reactor.lit_in(&region), 1);
+        assert!(reactor.is_lit([10, 10, 10]));
+        assert!(!reactor.is_lit([11, 11, 11]));
+        assert!(reactor.is_lit([13, 13, 13]));
+        assert!(!reactor.is_lit([14, 13, 13]));
+
+        let cuboids = reactor.lit_cuboids();
+        assert_eq!(cuboids.iter().map(|c| c.volume()).sum::<i128>(), 39);
+        for (a, b) in cuboids.iter().tuple_combinations() {
+            assert_eq!(a.intersect(b), None);
+        }
+        assert_eq!(
+            reactor.restrict(&region).lit_cuboids(),
+            vec!["x=10..10,y=10..10,z=10..10".parse().unwrap()]
+        );
+        assert_eq!("x=1..2,y=3..4".parse::<Cuboid<3>>(), Err(ParseCuboidError));
+        let square: Cuboid<2> = "x=1..2,y=3..4".parse().unwrap();
+        assert_eq!(square.to_string(), "x=1..2,y=3..4");
+        assert_eq!(
+            "x=1..2,y=3..4,w=5..6".parse::<Cuboid<3>>(),
+            Err(ParseCuboidError)
+        );
+    }
+
+    // Random steps within -8..8 along every axis, checked after each one
+    // against the set of lit voxels.
+    fn check_against_voxels<const N: usize>(steps: usize) {
+        let mut seed = 0x2022u64;
+        let mut rand = |range: i32| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) % range as u64) as i32
+        };
+        let mut input = String::new();
+        for _ in 0..steps {
+            let action = if rand(3) == 0 { "off" } else { "on" };
+            let coords = AXES[..N]
+                .iter()
+                .map(|axis| {
+                    let start = rand(16) - 8;
+                    format!("{}={}..{}", axis, start, start + rand(8))
+                })
+                .join(",");
+            input.push_str(&format!("{} {}\n", action, coords));
+        }
+        let steps = DayTwentyTwo::parse_input::<N>(&input);
+
+        let mut reactor = Reactor::default();
+        let mut voxels = HashSet::new();
+        for step in &steps {
+            let cube = step.cuboid.0.map(|c| c.start..=c.end);
+            for p in cube.into_iter().multi_cartesian_product() {
+                match step.at {
+                    ActionType::On => voxels.insert(p),
+                    ActionType::Off => voxels.remove(&p),
+                };
+            }
+            assert_eq!(reactor.apply(step), voxels.len() as i128);
+        }
+        assert_eq!(
+            DayTwentyTwo::solve(&steps, Backend::Compression),
+            voxels.len() as i128
+        );
+        let cuboids = reactor.lit_cuboids();
+        assert_eq!(
+            cuboids.iter().map(|c| c.volume()).sum::<i128>(),
+            voxels.len() as i128
+        );
+        for p in &voxels {
+            assert!(reactor.is_lit(p.clone().try_into().unwrap()));
+        }
+        for (a, b) in cuboids.iter().tuple_combinations() {
+            assert_eq!(a.intersect(b), None);
+        }
+    }
+
+    #[test]
+    fn any_dimension() {
+        check_against_voxels::<1>(50);
+        check_against_voxels::<2>(50);
+        check_against_voxels::<3>(30);
+        check_against_voxels::<4>(20);
+    }
+
+    // The previous engine: every signed intersection is appended to the
+    // list, without merging.
+    fn unmerged(steps: &[Step<3>]) -> i128 {
+        let mut previous: Vec<(Cuboid<3>, i128)> = vec![];
+        for step in steps {
+            let mut add = if step.at == ActionType::On {
+                vec![(step.cuboid, 1)]
+            } else {
+                vec![]
+            };
+            for (p, w) in &previous {
+                if let Some(intersection) = step.cuboid.intersect(p) {
+                    add.push((intersection, -w));
+                }
+            }
+            previous.append(&mut add);
+        }
+        previous.iter().map(|(c, w)| c.volume() * w).sum()
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_backends() {
+        use std::time::Instant;
+
+        let input = std::fs::read_to_string("src/inputs/22.txt").unwrap();
+        let steps = DayTwentyTwo::parse_input::<3>(&input);
+
+        let now = Instant::now();
+        let expected = unmerged(&steps);
+        println!("unmerged cuboids: {:?}", now.elapsed());
+        for backend in [Backend::Cuboids, Backend::Compression] {
+            let now = Instant::now();
+            assert_eq!(DayTwentyTwo::solve(&steps, backend), expected);
+            println!("{:?}: {:?}", backend, now.elapsed());
+        }
+    }
 }
@@ -2,7 +2,7 @@ use std::cmp::Ordering;
 use std::collections::BinaryHeap;
 use std::fmt::{Display, Formatter};
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 
 use hashbrown::HashMap;
 use itertools::Itertools;
@@ -32,11 +32,7 @@ impl Amphipod {
 }
 
 fn abs_diff(a: usize, b: usize) -> usize {
-    if a < b {
-        b - a
-    } else {
-        a - b
-    }
+    b.abs_diff(a)
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -63,7 +59,7 @@ impl<const R: usize> State<R> {
         fn decode_space(encoded_space: u64) -> Option<Amphipod> {
             match encoded_space {
                 0 => None,
-                1 | 2 | 3 | 4 => Some(Amphipod::from_room_index((encoded_space - 1) as usize)),
+                1..=4 => Some(Amphipod::from_room_index((encoded_space - 1) as usize)),
                 _ => unreachable!(),
             }
         }
@@ -108,7 +104,7 @@ impl<const R: usize> State<R> {
     }
 
     fn is_above_room(&self, x: usize) -> bool {
-        (x - 2) % 2 == 0 && (x - 2) / 2 < self.rooms.len()
+        (x - 2).is_multiple_of(2) && (x - 2) / 2 < self.rooms.len()
     }
 
     fn is_hallway_clear(&self, start_x: usize, target_x: usize) -> bool {
@@ -134,7 +130,7 @@ impl<const R: usize> State<R> {
 
     fn transitions(&self) -> Vec<(State<R>, usize)> {
         let mut transitions = self.room_to_hallway_transitions();
-        transitions.extend(self.hallway_to_room_transitions().into_iter());
+        transitions.extend(self.hallway_to_room_transitions());
         transitions
     }
 
@@ -389,7 +385,7 @@ fn solve<const R: usize>(initial_state: State<R>) -> usize {
 
 pub struct DayTwentyThree;
 impl Problem for DayTwentyThree {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let parsed_input = parse_input(input);
         let initial_state = State {
             hallway: [None; 11],
@@ -401,10 +397,10 @@ impl Problem for DayTwentyThree {
             ],
         };
 
-        solve(initial_state).to_string()
+        solve(initial_state).into()
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let parsed_input = parse_input(input);
         let initial_state = State {
             hallway: [None; 11],
@@ -416,6 +412,6 @@ impl Problem for DayTwentyThree {
             ],
         };
 
-        solve(initial_state).to_string()
+        solve(initial_state).into()
     }
 }
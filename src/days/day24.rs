@@ -1,11 +1,11 @@
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 use gag::Gag;
 use good_lp::{constraint, default_solver, variable, variables, Solution, SolverModel};
 
 pub struct DayTwentyFour {}
 
 impl DayTwentyFour {
-    fn solve(maximize: bool) -> String {
+    fn solve(maximize: bool) -> u64 {
         let print_gag = Gag::stdout().unwrap();
         let mut vars = variables!();
         let a1 = vars.add(variable().integer().min(1).max(9));
@@ -45,17 +45,17 @@ impl DayTwentyFour {
             .collect::<Vec<String>>()
             .join("");
         drop(print_gag);
-        r
+        r.parse().unwrap()
     }
 }
 
 impl Problem for DayTwentyFour {
-    fn part_one(&self, _input: &str) -> String {
-        Self::solve(true)
+    fn part_one(&self, _input: &str) -> Answer {
+        Self::solve(true).into()
     }
 
-    fn part_two(&self, _input: &str) -> String {
-        Self::solve(false)
+    fn part_two(&self, _input: &str) -> Answer {
+        Self::solve(false).into()
     }
 }
 
@@ -1,109 +1,282 @@
-use std::{collections::HashMap};
+use std::collections::HashMap;
+use std::fmt;
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 
 pub struct DayTwentyFive {}
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
-enum Direction {
-    Right,
-    Down,
+// Both herds as rows of bits, `words` u64s per row, bit c of a row in word
+// c / 64. Bits past the width are always zero.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct Herds {
+    height: usize,
+    width: usize,
+    words: usize,
+    east: Vec<u64>,
+    south: Vec<u64>,
 }
 
-impl DayTwentyFive {
-    fn read_input(input: &str) -> (HashMap<(usize, usize), Direction>, (usize, usize)) {
-        let mut ret = HashMap::new();
-        let dims = (
-            input.lines().filter(|l| !l.is_empty()).count(),
-            input
-                .lines().find(|l| !l.is_empty())
-                .unwrap()
-                .len(),
-        );
-        input
-            .lines()
-            .filter(|l| !l.is_empty())
-            .enumerate()
-            .for_each(|(i, line)| {
-                line.chars().enumerate().for_each(|(j, c)| {
-                    if let Some(direction) = match c {
-                        '.' => None,
-                        '>' => Some(Direction::Right),
-                        'v' => Some(Direction::Down),
-                        _ => panic!("Unexpected character {}", c),
-                    } {
-                        ret.insert((i, j), direction);
-                    }
-                });
-            });
-        (ret, dims)
+/// How the herds end up once a state repeats.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Nothing moves on the given step, counting from one.
+    Fixpoint(usize),
+    /// The state after `start` steps comes back every `length` steps.
+    Cycle { start: usize, length: usize },
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Fixpoint(step) => write!(f, "Nothing moves on step {}", step),
+            Outcome::Cycle { start, length } => write!(
+                f,
+                "The state after step {} repeats every {} steps",
+                start, length
+            ),
+        }
+    }
+}
+
+impl Herds {
+    fn row(bits: &[u64], words: usize, r: usize) -> &[u64] {
+        &bits[r * words..(r + 1) * words]
+    }
+
+    // out[c] = row[c + 1], wrapping around the width.
+    fn rotate_next(row: &[u64], width: usize, out: &mut [u64]) {
+        for i in 0..row.len() {
+            let carry = row.get(i + 1).map_or(0, |w| w << 63);
+            out[i] = row[i] >> 1 | carry;
+        }
+        let last = width - 1;
+        out[last / 64] |= (row[0] & 1) << (last % 64);
+    }
+
+    // out[c] = row[c - 1], wrapping around the width.
+    fn rotate_prev(row: &[u64], width: usize, out: &mut [u64]) {
+        for i in (0..row.len()).rev() {
+            let carry = if i > 0 { row[i - 1] >> 63 } else { 0 };
+            out[i] = row[i] << 1 | carry;
+        }
+        if !width.is_multiple_of(64) {
+            out[row.len() - 1] &= (1 << (width % 64)) - 1;
+        }
+        let last = width - 1;
+        out[0] |= row[last / 64] >> (last % 64) & 1;
     }
 
-    fn step(
-        hm: &HashMap<(usize, usize), Direction>,
-        height: usize,
-        width: usize,
-    ) -> (HashMap<(usize, usize), Direction>, bool) {
-        let mut new_hm = HashMap::new();
+    // Moves the east herd and then the south herd, returning whether any
+    // sea cucumber moved.
+    fn step(&mut self) -> bool {
+        let words = self.words;
         let mut moved = false;
-        for ((i, j), direction) in hm.iter() {
-            match direction {
-                Direction::Right => {
-                    let new_pos = (*i, (j + 1) % width);
-                    if !hm.contains_key(&new_pos) {
-                        new_hm.insert(new_pos, Direction::Right);
-                        moved = true;
-                    } else {
-                        new_hm.insert((*i, *j), Direction::Right);
-                    }
-                }
-                Direction::Down => {
-                    new_hm.insert((*i, *j), Direction::Down);
-                }
+        let mut occupied_ahead = vec![0; words];
+        let mut movers = vec![0; words];
+        let mut arrivals = vec![0; words];
+        for r in 0..self.height {
+            let occupied = (0..words)
+                .map(|i| self.east[r * words + i] | self.south[r * words + i])
+                .collect::<Vec<_>>();
+            Self::rotate_next(&occupied, self.width, &mut occupied_ahead);
+            for i in 0..words {
+                movers[i] = self.east[r * words + i] & !occupied_ahead[i];
+            }
+            Self::rotate_prev(&movers, self.width, &mut arrivals);
+            for i in 0..words {
+                moved |= movers[i] != 0;
+                self.east[r * words + i] = self.east[r * words + i] & !movers[i] | arrivals[i];
             }
         }
-        let mut new_new_hm = HashMap::new();
-        for ((i, j), direction) in new_hm.iter() {
-            match direction {
-                Direction::Right => {
-                    new_new_hm.insert((*i, *j), Direction::Right);
-                }
-                Direction::Down => {
-                    let new_pos = ((*i + 1) % height, *j);
-                    if !new_hm.contains_key(&new_pos) {
-                        new_new_hm.insert(new_pos, Direction::Down);
-                        moved = true;
-                    } else {
-                        new_new_hm.insert((*i, *j), Direction::Down);
+
+        // Each row of the south herd moves into the row below it if that
+        // was free before any of them moved.
+        let occupied = self
+            .east
+            .iter()
+            .zip(&self.south)
+            .map(|(e, s)| e | s)
+            .collect::<Vec<_>>();
+        let movers = (0..self.height)
+            .flat_map(|r| {
+                let below = Self::row(&occupied, words, (r + 1) % self.height);
+                Self::row(&self.south, words, r)
+                    .iter()
+                    .zip(below)
+                    .map(|(s, o)| s & !o)
+            })
+            .collect::<Vec<_>>();
+        for r in 0..self.height {
+            let above = (r + self.height - 1) % self.height;
+            for i in 0..words {
+                let leaving = movers[r * words + i];
+                moved |= leaving != 0;
+                self.south[r * words + i] =
+                    self.south[r * words + i] & !leaving | movers[above * words + i];
+            }
+        }
+        moved
+    }
+}
+
+impl fmt::Display for Herds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for r in 0..self.height {
+            for c in 0..self.width {
+                let bit = r * self.words + c / 64;
+                let c = match (
+                    self.east[bit] >> (c % 64) & 1,
+                    self.south[bit] >> (c % 64) & 1,
+                ) {
+                    (1, _) => '>',
+                    (_, 1) => 'v',
+                    _ => '.',
+                };
+                write!(f, "{}", c)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl DayTwentyFive {
+    fn read_input(input: &str) -> Herds {
+        let lines = input.lines().filter(|l| !l.is_empty()).collect::<Vec<_>>();
+        let width = lines[0].len();
+        let words = width.div_ceil(64);
+        let mut herds = Herds {
+            height: lines.len(),
+            width,
+            words,
+            east: vec![0; lines.len() * words],
+            south: vec![0; lines.len() * words],
+        };
+        for (i, line) in lines.iter().enumerate() {
+            for (j, c) in line.chars().enumerate() {
+                let herd = match c {
+                    '.' => continue,
+                    '>' => &mut herds.east,
+                    'v' => &mut herds.south,
+                    _ => panic!("Unexpected character {}", c),
+                };
+                herd[i * words + j / 64] |= 1 << (j % 64);
+            }
+        }
+        herds
+    }
+
+    // Steps until a state repeats, remembering every state seen so far.
+    fn simulate(mut herds: Herds) -> Outcome {
+        let mut seen = HashMap::new();
+        let mut steps = 0;
+        loop {
+            match seen.insert(herds.clone(), steps) {
+                Some(start) if steps - start == 1 => return Outcome::Fixpoint(steps),
+                Some(start) => {
+                    return Outcome::Cycle {
+                        start,
+                        length: steps - start,
                     }
                 }
+                None => {}
             }
+            herds.step();
+            steps += 1;
         }
-        (new_new_hm, moved)
+    }
+
+    pub fn settle(input: &str) {
+        println!("{}", Self::simulate(Self::read_input(input)));
     }
 }
 
 impl Problem for DayTwentyFive {
-    fn part_one(&self, input: &str) -> String {
-        let (mut hm, (height, width)) = DayTwentyFive::read_input(input);
-        let mut counter = 0;
-        loop {
-            let (hm_new, moved) = DayTwentyFive::step(&hm, height, width);
-            hm = hm_new;
+    fn part_one(&self, input: &str) -> Answer {
+        let mut herds = DayTwentyFive::read_input(input);
+        let mut counter = 1;
+        while herds.step() {
             counter += 1;
-            if !moved {
-                break;
-            }
         }
-        format!("{}", counter)
+        counter.into()
     }
 
-    fn part_two(&self, _input: &str) -> String {
-        String::new()
+    fn part_two(&self, _input: &str) -> Answer {
+        Answer::NotApplicable
     }
 }
 
 #[cfg(test)]
 mod tests {
-    
+    use super::*;
+
+    const EXAMPLE: &str = "v...>>.vv>
+.vv>>.vv..
+>>.>v>...v
+>>v>>.>.v.
+v>v.vv.v..
+>.>>..v...
+.vv..>.>v.
+v.v..>>v.v
+....v..v.>
+";
+
+    #[test]
+    fn steps_like_the_example() {
+        let mut herds = DayTwentyFive::read_input(EXAMPLE);
+        herds.step();
+        assert_eq!(
+            herds.to_string(),
+            "....>.>v.>
+v.v>.>v.v.
+>v>>..>v..
+>>v>v>.>.v
+.>v.v...v.
+v>>.>vvv..
+..v...>>..
+vv...>>vv.
+>.v.v..v.v
+"
+        );
+        assert_eq!(DayTwentyFive {}.part_one(EXAMPLE), Answer::Integer(58));
+        assert_eq!(
+            DayTwentyFive::simulate(DayTwentyFive::read_input(EXAMPLE)),
+            Outcome::Fixpoint(58)
+        );
+    }
+
+    #[test]
+    fn wraps_across_words() {
+        // A row wider than a word, with a herd straddling both edges.
+        let row = format!(">>{}>", ".".repeat(67));
+        let mut herds = DayTwentyFive::read_input(&row);
+        herds.step();
+        assert_eq!(herds.to_string(), format!(">.>{}>\n", ".".repeat(66)));
+        herds.step();
+        assert_eq!(herds.to_string(), format!(".>.>{}>\n", ".".repeat(65)));
+        herds.step();
+        assert_eq!(herds.to_string(), format!(">.>.>{}\n", ".".repeat(65)));
+    }
+
+    #[test]
+    fn detects_cycles() {
+        // One gap travels around the row forever.
+        let herds = DayTwentyFive::read_input(">>>.\n");
+        assert_eq!(
+            DayTwentyFive::simulate(herds),
+            Outcome::Cycle {
+                start: 0,
+                length: 4
+            }
+        );
+        // The south herd wraps from the bottom row to the top.
+        let herds = DayTwentyFive::read_input(".\nv\nv\n");
+        assert_eq!(
+            DayTwentyFive::simulate(herds),
+            Outcome::Cycle {
+                start: 0,
+                length: 3
+            }
+        );
+    }
 }
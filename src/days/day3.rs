@@ -1,4 +1,4 @@
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 use itertools::Itertools;
 
 pub struct DayThree {}
@@ -70,7 +70,7 @@ fn get_rating(input: impl AsRef<str>, binlen: usize, o2: bool) -> i32 {
 }
 
 impl Problem for DayThree {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let mut gamma = 0;
         let mut eps = 0;
         let mcb = get_most_common_bits(
@@ -88,16 +88,16 @@ impl Problem for DayThree {
             }
         });
 
-        format!("{}", eps * gamma)
+        (eps * gamma).into()
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let binlen = input.split('\n').next().unwrap().len();
 
         let oxigen = get_rating(input, binlen, true);
         let co2 = get_rating(input, binlen, false);
 
-        format!("{}", oxigen * co2)
+        (oxigen * co2).into()
     }
 }
 
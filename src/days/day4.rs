@@ -1,4 +1,4 @@
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 use std::str::FromStr;
 
 pub struct DayFour {}
@@ -76,7 +76,7 @@ impl FromStr for BingoBoard {
 }
 
 impl Problem for DayFour {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let mut input_parts = input.split("\n\n").filter(|l| !l.is_empty());
 
         let numbers = input_parts
@@ -95,15 +95,15 @@ impl Problem for DayFour {
             for board in &mut boards {
                 board.substitute_value(n, NEMPTY);
                 if board.won(NEMPTY) {
-                    return format!("{}", board.sum_rest(NEMPTY) * n);
+                    return (board.sum_rest(NEMPTY) * n).into();
                 }
             }
         }
 
-        "Fail".to_string()
+        Answer::Unsolved("no board wins".to_string())
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let mut input_parts = input.split("\n\n").filter(|l| !l.is_empty());
 
         let numbers = input_parts
@@ -141,11 +141,11 @@ impl Problem for DayFour {
             let boards_after = boards.iter().filter(|b| !b.won(NEMPTY)).count();
 
             if boards_after == 0 {
-                return format!("{}", boards[board_left_idx.unwrap()].sum_rest(NEMPTY) * n);
+                return (boards[board_left_idx.unwrap()].sum_rest(NEMPTY) * n).into();
             }
         }
 
-        String::new()
+        Answer::Unsolved("some boards never win".to_string())
     }
 }
 
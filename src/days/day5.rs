@@ -1,117 +1,411 @@
-use std::fmt::Debug;
-use std::{collections::HashMap, str::FromStr};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
 use itertools::Itertools;
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 
 pub struct DayFive {}
 
-#[derive(Debug)]
-struct Line<T: Ord + Debug> {
-    origin: Vec<T>,
-    destination: Vec<T>,
-}
-
-impl Line<i32> {
-    fn get_points(&self, consider_diagonals: bool) -> Vec<(i32, i32)> {
-        let (a, b, c, d) = (
-            self.origin[0],
-            self.origin[1],
-            self.destination[0],
-            self.destination[1],
-        );
-        if a == c {
-            if b > d { d..b + 1 } else { b..d + 1 }
-                .map(|y| (a, y))
-                .collect()
-        } else if d == b {
-            if c > a { a..c + 1 } else { c..a + 1 }
-                .map(|x| (x, b))
-                .collect()
-        } else if !consider_diagonals {
-            vec![]
+/// How overlapping vent points are counted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Rasterise into a grid over the bounding box.
+    Dense,
+    /// Rasterise into a hash map of the points hit.
+    Sparse,
+    /// Intersect every pair of lines instead of rasterising them.
+    Intersections,
+}
+
+// Bounding boxes up to this many cells are rasterised densely.
+const DENSE_LIMIT: u64 = 1 << 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Line {
+    origin: (i32, i32),
+    destination: (i32, i32),
+}
+
+impl Line {
+    fn is_axis_aligned(&self) -> bool {
+        self.origin.0 == self.destination.0 || self.origin.1 == self.destination.1
+    }
+
+    fn is_diagonal(&self) -> bool {
+        (self.destination.0 - self.origin.0).abs() == (self.destination.1 - self.origin.1).abs()
+    }
+
+    // Bresenham's algorithm, which hits exactly the lattice points of axis
+    // aligned and diagonal lines and the nearest ones at other slopes.
+    fn points(&self) -> impl Iterator<Item = (i32, i32)> {
+        let ((x0, y0), end) = (self.origin, self.destination);
+        let (dx, dy) = ((end.0 - x0).abs(), -(end.1 - y0).abs());
+        let (sx, sy) = ((end.0 - x0).signum(), (end.1 - y0).signum());
+        let mut err = dx + dy;
+        let mut next = Some((x0, y0));
+        std::iter::from_fn(move || {
+            let (x, y) = next?;
+            next = if (x, y) == end {
+                None
+            } else {
+                let e2 = 2 * err;
+                let mut p = (x, y);
+                if e2 >= dy {
+                    err += dy;
+                    p.0 += sx;
+                }
+                if e2 <= dx {
+                    err += dx;
+                    p.1 += sy;
+                }
+                Some(p)
+            };
+            Some((x, y))
+        })
+    }
+
+    // Start, unit step and number of steps of an axis-aligned or diagonal
+    // line.
+    fn steps(&self) -> ((i64, i64), (i64, i64), i64) {
+        let (x0, y0) = (self.origin.0 as i64, self.origin.1 as i64);
+        let (x1, y1) = (self.destination.0 as i64, self.destination.1 as i64);
+        let d = ((x1 - x0).signum(), (y1 - y0).signum());
+        ((x0, y0), d, (x1 - x0).abs().max((y1 - y0).abs()))
+    }
+
+    // The lattice points two axis-aligned or diagonal lines share.
+    fn intersection(&self, other: &Line) -> Vec<(i32, i32)> {
+        let cross = |a: (i64, i64), b: (i64, i64)| a.0 * b.1 - a.1 * b.0;
+        let (pa, da, la) = self.steps();
+        let (pb, db, lb) = other.steps();
+        let at = |t: i64| ((pa.0 + t * da.0) as i32, (pa.1 + t * da.1) as i32);
+        let offset = (pb.0 - pa.0, pb.1 - pa.1);
+
+        // Single points have no direction, so stand in for any line.
+        if la == 0 || lb == 0 {
+            let (p, line) = if la == 0 { (pa, other) } else { (pb, self) };
+            let p = (p.0 as i32, p.1 as i32);
+            return if line.contains(p) { vec![p] } else { vec![] };
+        }
+
+        let denom = cross(da, db);
+        if denom == 0 {
+            if cross(offset, da) != 0 {
+                return vec![];
+            }
+            // Collinear: project the other line onto this one.
+            let norm = da.0 * da.0 + da.1 * da.1;
+            let start = (offset.0 * da.0 + offset.1 * da.1) / norm;
+            let end = start + lb * (db.0 * da.0 + db.1 * da.1) / norm;
+            let (lo, hi) = (start.min(end).max(0), start.max(end).min(la));
+            return (lo..=hi).map(at).collect();
+        }
+
+        let (s, t) = (cross(offset, db), cross(offset, da));
+        if s % denom != 0 || t % denom != 0 {
+            return vec![];
+        }
+        let (s, t) = (s / denom, t / denom);
+        if (0..=la).contains(&s) && (0..=lb).contains(&t) {
+            vec![at(s)]
         } else {
-            assert!((a - c).abs() == (b - d).abs());
-            (0..(a - c).abs() + 1)
-                .map(|delta| (a + delta * (c - a).signum(), b + (d - b).signum() * delta))
-                .collect()
+            vec![]
         }
     }
+
+    fn contains(&self, p: (i32, i32)) -> bool {
+        let (start, d, len) = self.steps();
+        (0..=len).any(|t| (start.0 + t * d.0, start.1 + t * d.1) == (p.0 as i64, p.1 as i64))
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{},{} -> {},{}",
+            self.origin.0, self.origin.1, self.destination.0, self.destination.1
+        )
+    }
 }
 
 #[derive(Debug)]
-struct ParseLineError;
+pub struct ParseLineError;
+
+impl fmt::Display for ParseLineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Expected a line like 0,9 -> 5,9")
+    }
+}
+
+impl Error for ParseLineError {}
+
+#[derive(Debug)]
+pub struct OffLatticeLine(Line);
+
+impl fmt::Display for OffLatticeLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Line {} is neither axis-aligned nor diagonal, rasterise it instead",
+            self.0
+        )
+    }
+}
 
-impl FromStr for Line<i32> {
+impl Error for OffLatticeLine {}
+
+impl FromStr for Line {
     type Err = ParseLineError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split(" -> ");
+        let point = |p: &str| -> Result<(i32, i32), ParseLineError> {
+            let (x, y) = p.trim().split_once(',').ok_or(ParseLineError)?;
+            Ok((
+                x.parse().map_err(|_| ParseLineError)?,
+                y.parse().map_err(|_| ParseLineError)?,
+            ))
+        };
+        let (origin, destination) = s.split_once("->").ok_or(ParseLineError)?;
         Ok(Line {
-            origin: parts
-                .next()
-                .unwrap()
-                .split(',')
-                .map(|n_st| n_st.parse().unwrap())
-                .collect(),
-            destination: parts
-                .next()
-                .unwrap()
-                .split(',')
-                .map(|n_st| n_st.parse().unwrap())
-                .collect(),
+            origin: point(origin)?,
+            destination: point(destination)?,
         })
     }
 }
 
-impl Problem for DayFive {
-    fn part_one(&self, input: &str) -> String {
-        let lines = input
-            .split('\n')
-            .filter(|l| !l.is_empty())
-            .map(|l| Line::from_str(l).unwrap())
-            .collect_vec();
+// Where rasterised lines are counted.
+trait Canvas {
+    fn plot(&mut self, p: (i32, i32));
+    fn overlaps(&self) -> usize;
+}
 
-        let mut points = HashMap::new();
+struct Dense {
+    min: (i32, i32),
+    width: usize,
+    cells: Vec<u16>,
+}
 
-        for line in lines {
-            for point in line.get_points(false) {
-                if let std::collections::hash_map::Entry::Vacant(e) = points.entry(point) {
-                    e.insert(1);
-                } else {
-                    *points.get_mut(&point).unwrap() += 1;
-                }
-            }
+impl Dense {
+    fn new(lines: &[Line]) -> Self {
+        let ((x0, y0), (x1, y1)) = DayFive::bounds(lines);
+        let width = (x1 - x0 + 1) as usize;
+        Dense {
+            min: (x0, y0),
+            width,
+            cells: vec![0; width * (y1 - y0 + 1) as usize],
         }
+    }
+}
 
-        format!("{}", points.iter().filter(|((_x, _y), q)| **q > 1).count())
+impl Canvas for Dense {
+    fn plot(&mut self, p: (i32, i32)) {
+        let i = (p.1 - self.min.1) as usize * self.width + (p.0 - self.min.0) as usize;
+        self.cells[i] = self.cells[i].saturating_add(1);
     }
 
-    fn part_two(&self, input: &str) -> String {
-        let lines = input
+    fn overlaps(&self) -> usize {
+        self.cells.iter().filter(|&&n| n > 1).count()
+    }
+}
+
+#[derive(Default)]
+struct Sparse {
+    cells: HashMap<(i32, i32), u16>,
+}
+
+impl Canvas for Sparse {
+    fn plot(&mut self, p: (i32, i32)) {
+        let n = self.cells.entry(p).or_insert(0);
+        *n = n.saturating_add(1);
+    }
+
+    fn overlaps(&self) -> usize {
+        self.cells.values().filter(|&&n| n > 1).count()
+    }
+}
+
+impl DayFive {
+    fn read_input(input: &str) -> Result<Vec<Line>, ParseLineError> {
+        input
             .split('\n')
             .filter(|l| !l.is_empty())
-            .map(|l| Line::from_str(l).unwrap())
-            .collect_vec();
+            .map(Line::from_str)
+            .collect()
+    }
+
+    fn bounds(lines: &[Line]) -> ((i32, i32), (i32, i32)) {
+        let points = lines.iter().flat_map(|l| [l.origin, l.destination]);
+        let (x0, x1) = points.clone().map(|p| p.0).minmax().into_option().unwrap();
+        let (y0, y1) = points.map(|p| p.1).minmax().into_option().unwrap();
+        ((x0, y0), (x1, y1))
+    }
 
-        let mut points = HashMap::new();
+    // Dense when the bounding box is small enough, sparse otherwise.
+    fn choose_backend(lines: &[Line]) -> Backend {
+        let ((x0, y0), (x1, y1)) = Self::bounds(lines);
+        let cells = (x1 as i64 - x0 as i64 + 1) as u64 * (y1 as i64 - y0 as i64 + 1) as u64;
+        if cells <= DENSE_LIMIT {
+            Backend::Dense
+        } else {
+            Backend::Sparse
+        }
+    }
 
+    fn rasterise<C: Canvas>(mut canvas: C, lines: &[Line]) -> usize {
         for line in lines {
-            for point in line.get_points(true) {
-                if let std::collections::hash_map::Entry::Vacant(e) = points.entry(point) {
-                    e.insert(1);
-                } else {
-                    *points.get_mut(&point).unwrap() += 1;
-                }
-            }
+            line.points().for_each(|p| canvas.plot(p));
         }
+        canvas.overlaps()
+    }
 
-        format!("{}", points.iter().filter(|((_x, _y), q)| **q > 1).count())
+    fn intersections(lines: &[Line]) -> Result<usize, OffLatticeLine> {
+        if let Some(l) = lines
+            .iter()
+            .find(|l| !l.is_axis_aligned() && !l.is_diagonal())
+        {
+            return Err(OffLatticeLine(*l));
+        }
+        let mut shared = HashSet::new();
+        for (a, b) in lines.iter().tuple_combinations() {
+            shared.extend(a.intersection(b));
+        }
+        Ok(shared.len())
+    }
+
+    // Points covered by at least two lines.
+    fn overlaps(lines: &[Line], backend: Option<Backend>) -> Result<usize, OffLatticeLine> {
+        if lines.is_empty() {
+            return Ok(0);
+        }
+        match backend.unwrap_or_else(|| Self::choose_backend(lines)) {
+            Backend::Dense => Ok(Self::rasterise(Dense::new(lines), lines)),
+            Backend::Sparse => Ok(Self::rasterise(Sparse::default(), lines)),
+            Backend::Intersections => Self::intersections(lines),
+        }
+    }
+
+    pub fn count(
+        input: &str,
+        backend: Option<Backend>,
+        axis_aligned: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut lines = Self::read_input(input)?;
+        if axis_aligned {
+            lines.retain(Line::is_axis_aligned);
+        }
+        println!("{}", Self::overlaps(&lines, backend)?);
+        Ok(())
+    }
+}
+
+impl Problem for DayFive {
+    fn part_one(&self, input: &str) -> Answer {
+        let mut lines = Self::read_input(input).unwrap();
+        lines.retain(Line::is_axis_aligned);
+        Self::overlaps(&lines, None).unwrap().into()
+    }
+
+    fn part_two(&self, input: &str) -> Answer {
+        let lines = Self::read_input(input).unwrap();
+        Self::overlaps(&lines, None).unwrap().into()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    
+    use super::*;
+
+    const EXAMPLE: &str = "0,9 -> 5,9
+8,0 -> 0,8
+9,4 -> 3,4
+2,2 -> 2,1
+7,0 -> 7,4
+6,4 -> 2,0
+0,9 -> 2,9
+3,4 -> 1,4
+0,0 -> 8,8
+5,5 -> 8,2
+";
+
+    const BACKENDS: [Backend; 3] = [Backend::Dense, Backend::Sparse, Backend::Intersections];
+
+    fn line(s: &str) -> Line {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn example() {
+        assert_eq!(DayFive {}.part_one(EXAMPLE), Answer::Integer(5));
+        assert_eq!(DayFive {}.part_two(EXAMPLE), Answer::Integer(12));
+        let lines = DayFive::read_input(EXAMPLE).unwrap();
+        for backend in BACKENDS {
+            assert_eq!(DayFive::overlaps(&lines, Some(backend)).unwrap(), 12);
+        }
+        assert!("0,9 -> 5".parse::<Line>().is_err());
+    }
+
+    #[test]
+    fn bresenham() {
+        let points = |s| line(s).points().collect_vec();
+        assert_eq!(points("3,1 -> 3,1"), [(3, 1)]);
+        assert_eq!(points("9,7 -> 7,9"), [(9, 7), (8, 8), (7, 9)]);
+        assert_eq!(
+            points("0,0 -> 4,2"),
+            [(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]
+        );
+        assert_eq!(
+            points("0,0 -> -1,-3"),
+            [(0, 0), (0, -1), (-1, -2), (-1, -3)]
+        );
+
+        let lines = [
+            line("0,0 -> 4,2"),
+            line("4,0 -> 0,2"),
+            line("-3,5 -> 10,-1"),
+        ];
+        assert_eq!(
+            DayFive::overlaps(&lines, Some(Backend::Dense)).unwrap(),
+            DayFive::overlaps(&lines, Some(Backend::Sparse)).unwrap()
+        );
+        assert!(DayFive::overlaps(&lines, Some(Backend::Intersections)).is_err());
+    }
+
+    #[test]
+    fn backends_agree() {
+        let mut state = 0x9e3779b9u32;
+        let mut next = |n: i32| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state % n as u32) as i32 - n / 2
+        };
+        for _ in 0..20 {
+            let lines = (0..40)
+                .map(|_| {
+                    let (x, y, len) = (next(30), next(30), next(20));
+                    let (dx, dy) = match next(4) + 2 {
+                        0 => (1, 0),
+                        1 => (0, 1),
+                        2 => (1, 1),
+                        _ => (1, -1),
+                    };
+                    Line {
+                        origin: (x, y),
+                        destination: (x + dx * len, y + dy * len),
+                    }
+                })
+                .collect_vec();
+            let expected = DayFive::overlaps(&lines, Some(Backend::Sparse)).unwrap();
+            for backend in BACKENDS {
+                assert_eq!(DayFive::overlaps(&lines, Some(backend)).unwrap(), expected);
+            }
+        }
+        // Far apart points go to the sparse backend.
+        let lines = [line("0,0 -> 0,0"), line("100000,100000 -> 100000,100000")];
+        assert_eq!(DayFive::choose_backend(&lines), Backend::Sparse);
+    }
 }
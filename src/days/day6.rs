@@ -1,9 +1,12 @@
 use std::str::FromStr;
 
 use itertools::Itertools;
-use std::fmt::Debug;
+use std::error::Error;
+use std::fmt::{self, Debug};
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
+use crate::recurrence::{self, Matrix};
+use num::{BigUint, ToPrimitive, Zero};
 
 pub struct DaySix {}
 
@@ -21,43 +24,183 @@ where
         .collect_vec()
 }
 
-fn solve(input: &str, iterations: usize) -> String {
-    let population: Vec<usize> = parse_input(input);
+/// How often lanternfish give birth.
+#[derive(Clone, Copy, Debug)]
+pub struct Timers {
+    /// Days between births once a fish has given birth.
+    pub cycle: usize,
+    /// Days before a newborn fish first gives birth.
+    pub first_cycle: usize,
+}
+
+impl Default for Timers {
+    fn default() -> Self {
+        Timers {
+            cycle: 7,
+            first_cycle: 9,
+        }
+    }
+}
 
-    let mut quant: Vec<usize> = vec![0; 9];
+#[derive(Debug, PartialEq, Eq)]
+pub enum TimerError {
+    ZeroCycle,
+    OutOfRange { timer: usize, states: usize },
+}
 
-    for ind in population {
-        quant[ind] += 1;
+impl fmt::Display for TimerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimerError::ZeroCycle => write!(f, "Cycles must last at least one day"),
+            TimerError::OutOfRange { timer, states } => write!(
+                f,
+                "Timer {} is out of range, timers only go up to {}",
+                timer,
+                states - 1
+            ),
+        }
     }
+}
 
-    for _ in 0..iterations {
-        let reproducing = quant[0];
-        for i in 1..9 {
-            quant[i - 1] = quant[i];
+impl Error for TimerError {}
+
+impl Timers {
+    fn states(&self) -> usize {
+        self.cycle.max(self.first_cycle)
+    }
+
+    // One day of timers: each fish counts down, and those at zero restart
+    // the cycle and add a newborn fish.
+    fn transition(&self) -> Matrix {
+        let mut m = Matrix::new(self.states());
+        for i in 1..self.states() {
+            m.add(i - 1, i, 1);
         }
-        quant[8] = reproducing;
-        quant[6] = quant[6].checked_add(reproducing).unwrap();
+        m.add(self.cycle - 1, 0, 1);
+        m.add(self.first_cycle - 1, 0, 1);
+        m
     }
+}
 
-    format!(
-        "{}",
-        quant
-            .iter()
-            .fold(0usize, |acc, i| acc.checked_add(*i).unwrap())
-    )
+fn solve(
+    input: &str,
+    timers: Timers,
+    iterations: u64,
+    modulus: Option<&BigUint>,
+) -> Result<BigUint, TimerError> {
+    if timers.cycle == 0 || timers.first_cycle == 0 {
+        return Err(TimerError::ZeroCycle);
+    }
+    let population: Vec<usize> = parse_input(input);
+
+    let states = timers.states();
+    let mut quant = vec![BigUint::zero(); states];
+
+    for ind in population {
+        if ind >= states {
+            return Err(TimerError::OutOfRange { timer: ind, states });
+        }
+        quant[ind] += 1u32;
+    }
+
+    let total = recurrence::advance(&timers.transition(), &quant, iterations, modulus)
+        .into_iter()
+        .sum::<BigUint>();
+    Ok(match modulus {
+        Some(m) => total % m,
+        None => total,
+    })
+}
+
+pub fn population(
+    input: &str,
+    timers: Timers,
+    days: u64,
+    modulus: Option<u64>,
+) -> Result<(), Box<dyn Error>> {
+    match modulus {
+        Some(m) => println!(
+            "{} (mod {})",
+            solve(input, timers, days, Some(&m.into()))?,
+            m
+        ),
+        None => println!("{}", solve(input, timers, days, None)?),
+    }
+    Ok(())
 }
 
 impl Problem for DaySix {
-    fn part_one(&self, input: &str) -> String {
-        solve(input, 80)
+    fn part_one(&self, input: &str) -> Answer {
+        solve(input, Timers::default(), 80, None)
+            .unwrap()
+            .to_u64()
+            .unwrap()
+            .into()
     }
 
-    fn part_two(&self, input: &str) -> String {
-        solve(input, 256)
+    fn part_two(&self, input: &str) -> Answer {
+        solve(input, Timers::default(), 256, None)
+            .unwrap()
+            .to_u64()
+            .unwrap()
+            .into()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    
+    use super::*;
+
+    #[test]
+    fn example() {
+        let input = "3,4,3,1,2\n";
+        assert_eq!(
+            solve(input, Timers::default(), 18, None).unwrap(),
+            26u32.into()
+        );
+        assert_eq!(DaySix {}.part_one(input), Answer::Integer(5934));
+        assert_eq!(DaySix {}.part_two(input), Answer::Integer(26984457539));
+        let count = solve(input, Timers::default(), 100_000, None).unwrap();
+        assert_eq!(count.to_string().len(), 3785);
+        let modulus = BigUint::from(1_000_000_007u32);
+        assert_eq!(&count % &modulus, 663433029u32.into());
+        assert_eq!(
+            solve(input, Timers::default(), 100_000, Some(&modulus)).unwrap(),
+            663433029u32.into()
+        );
+    }
+
+    #[test]
+    fn other_timers() {
+        // Every fish doubles daily, newborns included.
+        let timers = Timers {
+            cycle: 1,
+            first_cycle: 1,
+        };
+        assert_eq!(solve("0", timers, 10, None), Ok(1024u32.into()));
+        // Fibonacci rabbits: newborns wait two days, then give birth daily.
+        let timers = Timers {
+            cycle: 1,
+            first_cycle: 2,
+        };
+        assert_eq!(solve("0", timers, 10, None), Ok(144u32.into()));
+        // Grown fish with a longer cycle than newborns.
+        let timers = Timers {
+            cycle: 3,
+            first_cycle: 2,
+        };
+        assert_eq!(solve("0", timers, 3, None), Ok(3u32.into()));
+        assert_eq!(
+            solve("0,3", timers, 1, None),
+            Err(TimerError::OutOfRange {
+                timer: 3,
+                states: 3
+            })
+        );
+        let timers = Timers {
+            cycle: 0,
+            first_cycle: 2,
+        };
+        assert_eq!(solve("0", timers, 1, None), Err(TimerError::ZeroCycle));
+    }
 }
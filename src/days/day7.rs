@@ -2,7 +2,7 @@ use itertools::Itertools;
 use std::fmt::Debug;
 use std::str::FromStr;
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 
 pub struct DaySeven {}
 
@@ -20,62 +20,185 @@ where
         .collect_vec()
 }
 
-fn median(numbers: &mut Vec<i64>) -> Vec<i64> {
-    numbers.sort();
+/// The fuel a crab burns to move some distance. The optimiser assumes
+/// the cost is convex and does not fall as the distance grows.
+pub trait FuelCost {
+    fn cost(&self, distance: i64) -> i128;
+}
+
+/// One unit of fuel per step.
+pub struct Linear;
+
+/// One more unit of fuel for each step than for the one before.
+pub struct Triangular;
+
+/// The square of the distance.
+pub struct Quadratic;
+
+impl FuelCost for Linear {
+    fn cost(&self, distance: i64) -> i128 {
+        distance as i128
+    }
+}
 
-    let mid = numbers.len() / 2;
-    if numbers.len() % 2 == 0 {
-        vec![numbers[mid - 1], numbers[mid]]
-    } else {
-        vec![numbers[mid]]
+impl FuelCost for Triangular {
+    fn cost(&self, distance: i64) -> i128 {
+        let n = distance as i128;
+        n.checked_mul(n + 1).unwrap() / 2
     }
 }
 
-fn triangular_number(n: i64) -> i128 {
-    let n = n as i128;
-    n.checked_mul(n + 1).unwrap() / 2
+impl FuelCost for Quadratic {
+    fn cost(&self, distance: i64) -> i128 {
+        (distance as i128).checked_mul(distance as i128).unwrap()
+    }
+}
+
+impl<F: Fn(i64) -> i128> FuelCost for F {
+    fn cost(&self, distance: i64) -> i128 {
+        self(distance)
+    }
+}
+
+fn total_cost<C: FuelCost + ?Sized>(positions: &[i64], cost: &C, target: i64) -> i128 {
+    positions
+        .iter()
+        .map(|n| cost.cost((n - target).abs()))
+        .fold(0i128, |acc, m| acc.checked_add(m).unwrap())
+}
+
+// The cheapest position and its total cost. The total is a sum of convex
+// functions and so convex itself: search for where it stops falling,
+// which only evaluates it a logarithmic number of times.
+fn cheapest<C: FuelCost + ?Sized>(positions: &[i64], cost: &C) -> (i64, i128) {
+    let (mut lo, mut hi) = positions.iter().copied().minmax().into_option().unwrap();
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if total_cost(positions, cost, mid) <= total_cost(positions, cost, mid + 1) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+    (lo, total_cost(positions, cost, lo))
+}
+
+// Crab positions straight from the input, without collecting them.
+fn positions(input: &str) -> impl Iterator<Item = i64> + '_ {
+    input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().unwrap())
+}
+
+// The cheapest position for the triangular cost, from one pass over the
+// input for the mean and one for the costs of at most three candidates.
+//
+// Away from crab positions the total cost T(p) = sum((d^2 + d) / 2) has
+// slope sum(p - x) + sum(sign(p - x)) / 2 = n(p - mean) + s / 2 with
+// |s| <= n. It falls below mean - 1/2 and rises above mean + 1/2, so the
+// real minimum lies within 1/2 of the mean, and by convexity the integer
+// one is between floor(mean - 1/2) and ceil(mean + 1/2).
+fn triangular_optimum(input: &str) -> (i64, i128) {
+    let (n, sum) = positions(input).fold((0i128, 0i128), |(n, sum), x| (n + 1, sum + x as i128));
+    let lo = (2 * sum - n).div_euclid(2 * n) as i64;
+    let hi = (2 * sum + n + 2 * n - 1).div_euclid(2 * n) as i64;
+
+    let mut totals = vec![0i128; (hi - lo + 1) as usize];
+    for x in positions(input) {
+        for (total, target) in totals.iter_mut().zip(lo..) {
+            *total = total
+                .checked_add(Triangular.cost((x - target).abs()))
+                .unwrap();
+        }
+    }
+    (lo..).zip(totals).min_by_key(|&(_, total)| total).unwrap()
+}
+
+pub fn print_cheapest(input: &str, cost: &dyn FuelCost) {
+    let positions: Vec<i64> = parse_input(input);
+    let (target, fuel) = cheapest(&positions, cost);
+    println!("Align at {} using {} fuel", target, fuel);
 }
 
 impl Problem for DaySeven {
-    fn part_one(&self, input: &str) -> String {
-        let mut positions: Vec<i64> = parse_input(input);
-        let median = median(&mut positions);
-
-        format!(
-            "{}",
-            median
-                .iter()
-                .map(|med| (med, positions.iter().map(|n| (n - med).abs()).sum::<i64>()))
-                .min_by(|(_, x), (_, y)| x.cmp(y))
-                .unwrap()
-                .1
-        )
+    fn part_one(&self, input: &str) -> Answer {
+        let positions: Vec<i64> = parse_input(input);
+        cheapest(&positions, &Linear).1.into()
     }
 
-    fn part_two(&self, input: &str) -> String {
-        let mut positions: Vec<i64> = parse_input(input);
-        let median = median(&mut positions);
-
-        let lb = *median.iter().min().unwrap();
-
-        format!(
-            "{:?}",
-            (lb - 1000..lb + 1000)
-                .map(|med| (
-                    med,
-                    positions
-                        .iter()
-                        .map(|n| triangular_number((n - med).abs()))
-                        .fold(0i128, |acc, m| acc.checked_add(m).unwrap())
-                ))
-                .min_by(|(_, x), (_, y)| x.cmp(y))
-                .unwrap()
-                .1
-        )
+    fn part_two(&self, input: &str) -> Answer {
+        triangular_optimum(input).1.into()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    
+    use super::*;
+
+    const EXAMPLE: &str = "16,1,2,0,4,2,7,1,2,14\n";
+
+    fn scan<C: FuelCost + ?Sized>(positions: &[i64], cost: &C) -> i128 {
+        let (lo, hi) = positions.iter().copied().minmax().into_option().unwrap();
+        (lo..=hi)
+            .map(|target| total_cost(positions, cost, target))
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn cost_models() {
+        let positions: Vec<i64> = parse_input(EXAMPLE);
+        assert_eq!(cheapest(&positions, &Linear), (2, 37));
+        assert_eq!(cheapest(&positions, &Triangular), (5, 168));
+        assert_eq!(DaySeven {}.part_one(EXAMPLE), Answer::Integer(37));
+        assert_eq!(DaySeven {}.part_two(EXAMPLE), Answer::Integer(168));
+
+        let cubic = |d: i64| (d as i128).pow(3);
+        let costs: [&dyn FuelCost; 4] = [&Linear, &Triangular, &Quadratic, &cubic];
+        let crabs = [
+            positions,
+            vec![5],
+            vec![0, 1000],
+            vec![-40, 7, 7, 7, 300, 301, -2],
+            (0..200).map(|i| (i * i * 7919) % 1013).collect(),
+        ];
+        for positions in &crabs {
+            for cost in costs {
+                assert_eq!(cheapest(positions, cost).1, scan(positions, cost));
+            }
+            let input = positions.iter().join(",");
+            assert_eq!(triangular_optimum(&input).1, scan(positions, &Triangular));
+        }
+    }
+
+    // Pseudo-random positions below 2000, like the puzzle's.
+    fn generated_input(crabs: usize) -> String {
+        let mut state = 0x2545f491u64;
+        (0..crabs)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state % 2000).to_string()
+            })
+            .join(",")
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_ten_million_crabs() {
+        use std::time::Instant;
+
+        let input = generated_input(10_000_000);
+
+        let now = Instant::now();
+        let (target, fuel) = triangular_optimum(&input);
+        println!("Mean window: {:?}", now.elapsed());
+
+        let now = Instant::now();
+        let positions: Vec<i64> = parse_input(&input);
+        assert_eq!(cheapest(&positions, &Triangular), (target, fuel));
+        println!("Convex search: {:?}", now.elapsed());
+    }
 }
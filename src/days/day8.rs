@@ -1,7 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::ops::Sub;
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 
 pub struct DayEight {}
 
@@ -49,7 +49,7 @@ impl Mapping {
 
         let remaining: Vec<&&str> = digits
             .iter()
-            .filter(|l| !vec![2, 3, 4, 7].contains(&l.len()))
+            .filter(|l| ![2, 3, 4, 7].contains(&l.len()))
             .collect();
 
         for cero in remaining.iter().filter(|l| l.len() == 6) {
@@ -128,39 +128,35 @@ impl Mapping {
 }
 
 impl Problem for DayEight {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let digits = parse_input(input);
-        let particular_digits = vec![2, 4, 3, 7];
-
-        format!(
-            "{}",
-            digits
-                .iter()
-                .flat_map(|(_v1, v2)| v2)
-                .map(|s| s.len())
-                .filter(|n| particular_digits.contains(n))
-                .count()
-        )
+        let particular_digits = [2, 4, 3, 7];
+
+        digits
+            .iter()
+            .flat_map(|(_v1, v2)| v2)
+            .map(|s| s.len())
+            .filter(|n| particular_digits.contains(n))
+            .count()
+            .into()
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let digits = parse_input(input);
-        format!(
-            "{}",
-            digits
-                .iter()
-                .map(|(allnums, digits)| {
-                    let mapping = Mapping::new(allnums);
-                    digits
-                        .iter()
-                        .map(|d| {
-                            let digit_chars = d.chars().collect::<HashSet<_>>();
-                            mapping.get(&digit_chars)
-                        })
-                        .fold(0, |acc, new| acc * 10 + new)
-                })
-                .sum::<i128>()
-        )
+        digits
+            .iter()
+            .map(|(allnums, digits)| {
+                let mapping = Mapping::new(allnums);
+                digits
+                    .iter()
+                    .map(|d| {
+                        let digit_chars = d.chars().collect::<HashSet<_>>();
+                        mapping.get(&digit_chars)
+                    })
+                    .fold(0, |acc, new| acc * 10 + new)
+            })
+            .sum::<i128>()
+            .into()
     }
 }
 
@@ -1,7 +1,7 @@
 extern crate queues;
 use std::collections::HashSet;
 
-use crate::problem::problemdef::Problem;
+use crate::problem::problemdef::{Answer, Problem};
 use queues::*;
 pub struct DayNine {}
 
@@ -50,7 +50,7 @@ impl DayNine {
 }
 
 impl Problem for DayNine {
-    fn part_one(&self, input: &str) -> String {
+    fn part_one(&self, input: &str) -> Answer {
         let mut total_risk_level: u32 = 0;
         let map = DayNine::parse_input(input);
         for i in 0..map.len() {
@@ -84,10 +84,10 @@ impl Problem for DayNine {
                 }
             }
         }
-        format!("{}", total_risk_level)
+        total_risk_level.into()
     }
 
-    fn part_two(&self, input: &str) -> String {
+    fn part_two(&self, input: &str) -> Answer {
         let map = DayNine::parse_input(input);
         let mut sinks = vec![];
         let mut basin_sizes = vec![];
@@ -155,14 +155,12 @@ impl Problem for DayNine {
             );*/
         }
         basin_sizes.sort();
-        format!(
-            "{}",
-            basin_sizes
-                .iter()
-                .rev()
-                .take(3)
-                .fold(1usize, |acc, i| acc.checked_mul(*i).unwrap())
-        )
+        basin_sizes
+            .iter()
+            .rev()
+            .take(3)
+            .fold(1usize, |acc, i| acc.checked_mul(*i).unwrap())
+            .into()
     }
 }
 
@@ -1,13 +1,17 @@
 #![feature(iter_next_chunk,iter_advance_by)]
 
 mod days;
+mod ocr;
 mod problem;
+mod recurrence;
+mod snailfish;
 
-use clap::Parser;
+use clap::{Args as ClapArgs, Parser, Subcommand};
 use days::*;
 use problem::problemdef::{BenchmarkResult, Problem};
 use std::error::Error;
 use std::fs;
+use std::path::PathBuf;
 
 extern crate serde;
 extern crate serde_json;
@@ -28,6 +32,287 @@ struct Args {
 
     #[arg(short, long, default_value_t = false)]
     run_all: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Print the day 15 cave with the lowest-risk path highlighted
+    CavePath {
+        #[arg(short, long, default_value = "src/inputs/15.txt")]
+        input: PathBuf,
+
+        /// Use the five times larger map of part two
+        #[arg(short, long, default_value_t = false)]
+        full: bool,
+
+        /// Also write a PPM heatmap of risk and distance from the start
+        #[arg(long)]
+        heatmap: Option<PathBuf>,
+    },
+    /// List the day 12 paths through the cave system
+    CavePaths {
+        #[arg(short, long, default_value = "src/inputs/12.txt")]
+        input: PathBuf,
+
+        #[command(flatten)]
+        revisits: RevisitArgs,
+
+        /// Only print the first paths found
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+    /// Write the day 12 cave system as a Graphviz DOT file
+    ExportDot {
+        #[arg(short, long, default_value = "src/inputs/12.txt")]
+        input: PathBuf,
+
+        #[arg(short, long, default_value = "caves.dot")]
+        output: PathBuf,
+
+        /// Label the edges with the number of paths using them
+        #[arg(short, long, default_value_t = false)]
+        usage: bool,
+
+        #[command(flatten)]
+        revisits: RevisitArgs,
+    },
+    /// Print the day 19 scanner transforms and write the beacon map as a
+    /// point cloud (.ply, .xyz or .csv)
+    ScannerMap {
+        #[arg(short, long, default_value = "src/inputs/19.txt")]
+        input: PathBuf,
+
+        #[arg(short, long, default_value = "beacons.ply")]
+        output: PathBuf,
+    },
+    /// Count the points covered by at least two day 5 vent lines
+    Vents {
+        #[arg(short, long, default_value = "src/inputs/5.txt")]
+        input: PathBuf,
+
+        /// Chosen from the bounding box unless given
+        #[arg(long, value_enum)]
+        backend: Option<VentBackend>,
+
+        /// Skip the lines that are not horizontal or vertical
+        #[arg(long, default_value_t = false)]
+        axis_aligned: bool,
+    },
+    /// Count the day 6 lanternfish after any number of days
+    Lanternfish {
+        #[arg(short, long, default_value = "src/inputs/6.txt")]
+        input: PathBuf,
+
+        #[arg(short, long, default_value_t = 256)]
+        days: u64,
+
+        /// Only count modulo this, for more days than exact counts fit
+        #[arg(short, long)]
+        modulus: Option<u64>,
+
+        /// Days between births once a fish has given birth
+        #[arg(long, default_value_t = 7)]
+        cycle: usize,
+
+        /// Days before a newborn fish first gives birth
+        #[arg(long, default_value_t = 9)]
+        first_cycle: usize,
+    },
+    /// Find where the day 7 crabs align for the least fuel
+    CrabFuel {
+        #[arg(short, long, default_value = "src/inputs/7.txt")]
+        input: PathBuf,
+
+        #[arg(short, long, value_enum, default_value_t = CrabCost::Triangular)]
+        cost: CrabCost,
+
+        /// Exponent of the power cost
+        #[arg(short, long, default_value_t = 3)]
+        exponent: u32,
+    },
+    /// Count the day 14 polymer elements after any number of steps
+    Polymer {
+        #[arg(short, long, default_value = "src/inputs/14.txt")]
+        input: PathBuf,
+
+        #[arg(short, long, default_value_t = 40)]
+        steps: u64,
+
+        /// Only count modulo this, for more steps than exact counts fit
+        #[arg(short, long)]
+        modulus: Option<u64>,
+
+        /// Print the element counts after every step
+        #[arg(long, default_value_t = false, conflicts_with = "modulus")]
+        each_step: bool,
+    },
+    /// Write every day 20 enhancement step as a PBM frame and print the
+    /// lit pixel counts
+    TrenchFrames {
+        #[arg(short, long, default_value = "src/inputs/20.txt")]
+        input: PathBuf,
+
+        #[arg(short, long, default_value_t = 50)]
+        steps: usize,
+
+        /// Directory for the step_NNN.pbm frames
+        #[arg(short, long, default_value = "frames")]
+        output: PathBuf,
+    },
+    /// Step the day 25 sea cucumbers until a state repeats, reporting
+    /// either the step nothing moves on or the cycle they fall into
+    SeaCucumbers {
+        #[arg(short, long, default_value = "src/inputs/25.txt")]
+        input: PathBuf,
+    },
+    /// Count the cubes left on by the day 22 reboot steps
+    Reboot {
+        #[arg(short, long, default_value = "src/inputs/22.txt")]
+        input: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = ReactorBackend::Cuboids)]
+        backend: ReactorBackend,
+
+        /// Only apply the initialization steps within -50..50
+        #[arg(long, default_value_t = false)]
+        initialization: bool,
+
+        /// Print the count after every step, with the cuboid backend
+        #[arg(long, default_value_t = false, conflicts_with = "backend")]
+        each_step: bool,
+    },
+    /// Add up the day 18 snailfish numbers and print the reduced sum
+    SnailfishSum {
+        #[arg(short, long, default_value = "src/inputs/18.txt")]
+        input: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = SnailfishBackend::Flat)]
+        backend: SnailfishBackend,
+    },
+    /// Add two snailfish numbers and print every step of the reduction
+    SnailfishExplain {
+        a: String,
+
+        b: String,
+
+        #[arg(long, value_enum, default_value_t = SnailfishBackend::Flat)]
+        backend: SnailfishBackend,
+    },
+    /// List the pairs of day 18 snailfish numbers with the largest sums
+    SnailfishPairs {
+        #[arg(short, long, default_value = "src/inputs/18.txt")]
+        input: PathBuf,
+
+        /// How many pairs to list
+        #[arg(short, long, default_value_t = 10)]
+        top: usize,
+    },
+    /// Count the cubes left on by the day 22 reboot steps inside a region
+    ReactorQuery {
+        #[arg(short, long, default_value = "src/inputs/22.txt")]
+        input: PathBuf,
+
+        /// Query region, as in the reboot steps: x=-10..10,y=0..5,z=3..3
+        region: String,
+
+        /// Also tell whether the cube at x,y,z is on
+        #[arg(short, long, allow_hyphen_values = true)]
+        point: Option<String>,
+
+        /// List disjoint cuboids covering the lit cubes in the region
+        #[arg(short, long, default_value_t = false)]
+        list: bool,
+    },
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum VentBackend {
+    /// A grid over the bounding box
+    Dense,
+    /// A hash map of the points hit
+    Sparse,
+    /// Intersections of every pair of lines
+    Intersections,
+}
+
+impl From<VentBackend> for day5::Backend {
+    fn from(backend: VentBackend) -> Self {
+        match backend {
+            VentBackend::Dense => day5::Backend::Dense,
+            VentBackend::Sparse => day5::Backend::Sparse,
+            VentBackend::Intersections => day5::Backend::Intersections,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum CrabCost {
+    /// One unit of fuel per step
+    Linear,
+    /// One more unit for each step than for the one before
+    Triangular,
+    /// The square of the distance
+    Quadratic,
+    /// The distance to the power of --exponent
+    Power,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum SnailfishBackend {
+    /// Boxed pairs
+    Tree,
+    /// Regular numbers with their depths
+    Flat,
+}
+
+impl From<SnailfishBackend> for snailfish::Backend {
+    fn from(backend: SnailfishBackend) -> Self {
+        match backend {
+            SnailfishBackend::Tree => snailfish::Backend::Tree,
+            SnailfishBackend::Flat => snailfish::Backend::Flat,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ReactorBackend {
+    /// Merged signed cuboids
+    Cuboids,
+    /// Coordinate compression
+    Compression,
+}
+
+impl From<ReactorBackend> for day22::Backend {
+    fn from(backend: ReactorBackend) -> Self {
+        match backend {
+            ReactorBackend::Cuboids => day22::Backend::Cuboids,
+            ReactorBackend::Compression => day22::Backend::Compression,
+        }
+    }
+}
+
+#[derive(ClapArgs, Debug)]
+struct RevisitArgs {
+    /// Let a single small cave be visited this many extra times
+    #[arg(long, conflicts_with = "budget")]
+    one_cave: Option<usize>,
+
+    /// Allow this many extra small cave visits in total
+    #[arg(long)]
+    budget: Option<usize>,
+}
+
+impl RevisitArgs {
+    fn rule(&self) -> day12::RevisitRule {
+        match (self.one_cave, self.budget) {
+            (Some(n), _) => day12::RevisitRule::OneCave(n),
+            (_, Some(n)) => day12::RevisitRule::Budget(n),
+            _ => day12::RevisitRule::Never,
+        }
+    }
 }
 
 fn day_to_problem(day: usize) -> Option<Box<dyn Problem>> {
@@ -76,10 +361,130 @@ fn run_problem(
     Ok(None)
 }
 
+fn run_command(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::CavePath {
+            input,
+            full,
+            heatmap,
+        } => day15::DayFifteen::show_path(&fs::read_to_string(input)?, full, heatmap.as_deref()),
+        Command::CavePaths {
+            input,
+            revisits,
+            limit,
+        } => day12::DayTwelve::list_paths(&fs::read_to_string(input)?, revisits.rule(), limit),
+        Command::ExportDot {
+            input,
+            output,
+            usage,
+            revisits,
+        } => day12::DayTwelve::export_dot(
+            &fs::read_to_string(input)?,
+            &output,
+            usage.then(|| revisits.rule()),
+        ),
+        Command::ScannerMap { input, output } => {
+            day19::DayNineteen::export_map(&fs::read_to_string(input)?, &output)
+        }
+        Command::Vents {
+            input,
+            backend,
+            axis_aligned,
+        } => day5::DayFive::count(
+            &fs::read_to_string(input)?,
+            backend.map(Into::into),
+            axis_aligned,
+        ),
+        Command::Lanternfish {
+            input,
+            days,
+            modulus,
+            cycle,
+            first_cycle,
+        } => {
+            let timers = day6::Timers { cycle, first_cycle };
+            day6::population(&fs::read_to_string(input)?, timers, days, modulus)
+        }
+        Command::CrabFuel {
+            input,
+            cost,
+            exponent,
+        } => {
+            let power = move |d: i64| (d as i128).checked_pow(exponent).unwrap();
+            let cost: &dyn day7::FuelCost = match cost {
+                CrabCost::Linear => &day7::Linear,
+                CrabCost::Triangular => &day7::Triangular,
+                CrabCost::Quadratic => &day7::Quadratic,
+                CrabCost::Power => &power,
+            };
+            day7::print_cheapest(&fs::read_to_string(input)?, cost);
+            Ok(())
+        }
+        Command::Polymer {
+            input,
+            steps,
+            modulus,
+            each_step,
+        } => {
+            let contents = fs::read_to_string(input)?;
+            if each_step {
+                return day14::DayFourteen::print_histograms(&contents, steps.try_into()?);
+            }
+            day14::DayFourteen::print_counts(&contents, steps, modulus);
+            Ok(())
+        }
+        Command::TrenchFrames {
+            input,
+            steps,
+            output,
+        } => day20::DayTwenty::export_frames(&fs::read_to_string(input)?, steps, &output),
+        Command::SeaCucumbers { input } => {
+            day25::DayTwentyFive::settle(&fs::read_to_string(input)?);
+            Ok(())
+        }
+        Command::Reboot {
+            input,
+            backend,
+            initialization,
+            each_step,
+        } => {
+            let contents = fs::read_to_string(input)?;
+            if each_step {
+                day22::DayTwentyTwo::reboot_steps(&contents, initialization);
+            } else {
+                let lit = day22::DayTwentyTwo::reboot(&contents, backend.into(), initialization);
+                println!("{}", lit);
+            }
+            Ok(())
+        }
+        Command::SnailfishSum { input, backend } => {
+            day18::DayEighteen::print_sum(&fs::read_to_string(input)?, backend.into())
+        }
+        Command::SnailfishExplain { a, b, backend } => {
+            day18::DayEighteen::explain_sum(&a, &b, backend.into())
+        }
+        Command::SnailfishPairs { input, top } => {
+            day18::DayEighteen::print_best_pairs(&fs::read_to_string(input)?, top)
+        }
+        Command::ReactorQuery {
+            input,
+            region,
+            point,
+            list,
+        } => {
+            day22::DayTwentyTwo::query(&fs::read_to_string(input)?, &region, point.as_deref(), list)
+        }
+    }
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     env_logger::init();
     let args = Args::parse();
 
+    if let Some(command) = args.command {
+        return run_command(command);
+    }
+
     if args.day == NOPROBLEM && !args.run_all {
         return Err("Either --day <day> or --run-all must be provided.".into());
     }
@@ -107,7 +512,7 @@ fn main() -> Result<(), Box<dyn Error>> {
             let serialized = serde_json::to_string(&bench_results).unwrap();
             println!("{}\n", serialized);
             let serialized = serde_json::to_value(&bench_results).unwrap();
-            println!("{}", json_to_table(&serialized).to_string());
+            println!("{}", json_to_table(&serialized));
         }
     } else {
         let p = day_to_problem(args.day).ok_or("Day is not implemented")?;
@@ -116,7 +521,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                 let serialized = serde_json::to_string_pretty(&br).unwrap();
                 println!("{}", serialized);
                 let serialized = serde_json::to_value(&br).unwrap();
-                println!("{}", json_to_table(&serialized).to_string());
+                println!("{}", json_to_table(&serialized));
             }
             Ok(None) => {}
             Err(e) => return Err(e),
@@ -0,0 +1,280 @@
+const SMALL_HEIGHT: usize = 6;
+const SMALL_WIDTH: usize = 4;
+const SMALL_LETTERS: [(char, [&str; 6]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+const LARGE_HEIGHT: usize = 10;
+const LARGE_WIDTH: usize = 6;
+#[rustfmt::skip]
+const LARGE_LETTERS: [(char, [&str; 10]); 15] = [
+    ('A', ["..##..",
+           ".#..#.",
+           "#....#",
+           "#....#",
+           "#....#",
+           "######",
+           "#....#",
+           "#....#",
+           "#....#",
+           "#....#"]),
+    ('B', ["#####.",
+           "#....#",
+           "#....#",
+           "#....#",
+           "#####.",
+           "#....#",
+           "#....#",
+           "#....#",
+           "#....#",
+           "#####."]),
+    ('C', [".####.",
+           "#....#",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#....#",
+           ".####."]),
+    ('E', ["######",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#####.",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "######"]),
+    ('F', ["######",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#####.",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#....."]),
+    ('G', [".####.",
+           "#....#",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#..###",
+           "#....#",
+           "#....#",
+           "#...##",
+           ".###.#"]),
+    ('H', ["#....#",
+           "#....#",
+           "#....#",
+           "#....#",
+           "######",
+           "#....#",
+           "#....#",
+           "#....#",
+           "#....#",
+           "#....#"]),
+    ('J', ["...###",
+           "....#.",
+           "....#.",
+           "....#.",
+           "....#.",
+           "....#.",
+           "....#.",
+           "#...#.",
+           "#...#.",
+           ".###.."]),
+    ('K', ["#....#",
+           "#...#.",
+           "#..#..",
+           "#.#...",
+           "##....",
+           "##....",
+           "#.#...",
+           "#..#..",
+           "#...#.",
+           "#....#"]),
+    ('L', ["#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "######"]),
+    ('N', ["#....#",
+           "##...#",
+           "##...#",
+           "#.#..#",
+           "#.#..#",
+           "#..#.#",
+           "#..#.#",
+           "#...##",
+           "#...##",
+           "#....#"]),
+    ('P', ["#####.",
+           "#....#",
+           "#....#",
+           "#....#",
+           "#####.",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#.....",
+           "#....."]),
+    ('R', ["#####.",
+           "#....#",
+           "#....#",
+           "#....#",
+           "#####.",
+           "#..#..",
+           "#...#.",
+           "#...#.",
+           "#....#",
+           "#....#"]),
+    ('X', ["#....#",
+           "#....#",
+           ".#..#.",
+           ".#..#.",
+           "..##..",
+           "..##..",
+           ".#..#.",
+           ".#..#.",
+           "#....#",
+           "#....#"]),
+    ('Z', ["######",
+           ".....#",
+           ".....#",
+           "....#.",
+           "...#..",
+           "..#...",
+           ".#....",
+           "#.....",
+           "#.....",
+           "######"]),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnknownGlyphError {
+    pub position: usize,
+}
+
+/// Reads the letters drawn on a `width` x `height` grid, where `lit(x, y)`
+/// tells whether a dot is present. Both the 4x6 font (letters five columns
+/// apart) and the 6x10 font (letters eight columns apart) are recognised,
+/// chosen from the height of the drawing.
+pub fn recognize(
+    width: usize,
+    height: usize,
+    lit: impl Fn(usize, usize) -> bool,
+) -> Result<String, UnknownGlyphError> {
+    match height {
+        SMALL_HEIGHT => read_glyphs(width, SMALL_WIDTH + 1, &SMALL_LETTERS, lit),
+        LARGE_HEIGHT => read_glyphs(width, LARGE_WIDTH + 2, &LARGE_LETTERS, lit),
+        _ => Err(UnknownGlyphError { position: 0 }),
+    }
+}
+
+fn read_glyphs<const H: usize>(
+    width: usize,
+    spacing: usize,
+    letters: &[(char, [&str; H])],
+    lit: impl Fn(usize, usize) -> bool,
+) -> Result<String, UnknownGlyphError> {
+    let mut r = String::new();
+    for (position, x0) in (0..width).step_by(spacing).enumerate() {
+        let (c, _) = letters
+            .iter()
+            .find(|(_, rows)| {
+                rows.iter().enumerate().all(|(y, row)| {
+                    row.chars()
+                        .enumerate()
+                        .all(|(dx, c)| (c == '#') == lit(x0 + dx, y))
+                })
+            })
+            .ok_or(UnknownGlyphError { position })?;
+        r.push(*c);
+    }
+    Ok(r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_art(art: &str) -> (usize, usize, Vec<Vec<bool>>) {
+        let rows = art
+            .lines()
+            .map(|l| l.chars().map(|c| c == '#').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        (rows[0].len(), rows.len(), rows)
+    }
+
+    #[test]
+    fn small_font() {
+        let (w, h, rows) = from_art(
+            "#..#.####.#....####.#..#...##.###..#..#
+#..#....#.#....#....#..#....#.#..#.#.#.
+####...#..#....###..####....#.#..#.##..
+#..#..#...#....#....#..#....#.###..#.#.
+#..#.#....#....#....#..#.#..#.#.#..#.#.
+#..#.####.####.####.#..#..##..#..#.#..#",
+        );
+        let lit = |x: usize, y: usize| rows[y].get(x).cloned().unwrap_or(false);
+        assert_eq!(recognize(w, h, lit), Ok("HZLEHJRK".to_string()));
+    }
+
+    #[test]
+    fn large_font() {
+        let (w, h, rows) = from_art(
+            "#....#..#####.
+#....#..#....#
+#....#..#....#
+#....#..#....#
+######..#####.
+#....#..#.....
+#....#..#.....
+#....#..#.....
+#....#..#.....
+#....#..#.....",
+        );
+        let lit = |x: usize, y: usize| rows[y].get(x).cloned().unwrap_or(false);
+        assert_eq!(recognize(w, h, lit), Ok("HP".to_string()));
+    }
+
+    #[test]
+    fn unknown_glyph() {
+        let (w, h, rows) = from_art(
+            "####.####
+#..#.#..#
+#..#.#..#
+#..#.#..#
+#..#.#..#
+####.####",
+        );
+        let lit = |x: usize, y: usize| rows[y].get(x).cloned().unwrap_or(false);
+        assert_eq!(recognize(w, h, lit), Err(UnknownGlyphError { position: 0 }));
+    }
+}
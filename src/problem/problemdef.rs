@@ -1,18 +1,78 @@
+use std::fmt;
 use std::time::Instant;
 
 pub trait Problem {
-    fn part_one(&self, input: &str) -> String;
-    fn part_two(&self, input: &str) -> String;
+    fn part_one(&self, input: &str) -> Answer;
+    fn part_two(&self, input: &str) -> Answer;
+}
+
+/// What a part of a puzzle comes out as.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Answer {
+    /// A number, as most puzzles ask for.
+    Integer(i128),
+    /// A short string, such as letters read off a display.
+    Text(String),
+    /// Several lines of characters that could not be read as text.
+    Art(String),
+    /// The puzzle has no such part.
+    NotApplicable,
+    /// No answer was found, for the given reason.
+    Unsolved(String),
+}
+
+macro_rules! answer_from_int {
+    ($($t:ty)*) => {$(
+        impl From<$t> for Answer {
+            fn from(n: $t) -> Self {
+                Answer::Integer(n as i128)
+            }
+        }
+    )*};
+}
+
+answer_from_int!(u8 u16 u32 u64 usize i8 i16 i32 i64 i128 isize);
+
+impl From<u128> for Answer {
+    fn from(n: u128) -> Self {
+        Answer::Integer(i128::try_from(n).expect("answer does not fit an i128"))
+    }
+}
+
+impl Answer {
+    // A single line for the benchmark table.
+    fn summary(&self) -> String {
+        match self {
+            Answer::Art(art) => format!("({} lines of art)", art.lines().count()),
+            Answer::NotApplicable => "n/a".to_string(),
+            Answer::Unsolved(_) => "unsolved".to_string(),
+            a => a.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Answer::Integer(n) => write!(f, "{}", n),
+            Answer::Text(s) => write!(f, "{}", s),
+            Answer::Art(art) => write!(f, "{}", art.trim_end()),
+            Answer::NotApplicable => write!(f, "Not applicable"),
+            Answer::Unsolved(reason) => write!(f, "Unsolved: {}", reason),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct BenchmarkResult {
     problem: usize,
     //durations: Vec<Duration>,
-    mean_millis_pt1: f64,
-    var_millis_squared_pt1: f64,
-    mean_millis_pt2: f64,
-    var_millis_squared_pt2: f64,
+    answer_pt1: String,
+    mean_millis_pt1: Option<f64>,
+    var_millis_squared_pt1: Option<f64>,
+    answer_pt2: String,
+    mean_millis_pt2: Option<f64>,
+    var_millis_squared_pt2: Option<f64>,
 }
 
 impl BenchmarkResult {
@@ -29,62 +89,102 @@ impl dyn Problem {
         println!["{}", self.part_two(input)];
     }
 
-    pub fn benchmark(&self, input: &str) -> BenchmarkResult {
-        let now = Instant::now();
+    // Mean and variance in milliseconds of a few runs of one part, or None
+    // when the part does not apply and there is nothing to time.
+    fn time_part(
+        &self,
+        part: fn(&Self, &str) -> Answer,
+        name: &str,
+        input: &str,
+    ) -> (Answer, Option<(f64, f64)>) {
         const N: u32 = 3;
 
-        let mut durations1 = vec![];
-        let mut durations2 = vec![];
-
-        for _ in 0..N {
-            let now = Instant::now();
-            self.part_one(input);
-            let elapsed = now.elapsed();
-            durations1.push(elapsed);
-        }
-
-        let elapsed = now.elapsed();
-        println!("Part 1 took {:.5?} on average", elapsed / N);
-
         let now = Instant::now();
+        let mut durations = vec![];
+        let mut answer = Answer::NotApplicable;
 
         for _ in 0..N {
             let now = Instant::now();
-            self.part_two(input);
+            answer = part(self, input);
             let elapsed = now.elapsed();
-            durations2.push(elapsed);
+            if answer == Answer::NotApplicable {
+                println!("{} does not apply, skipping it", name);
+                return (answer, None);
+            }
+            durations.push(elapsed);
         }
 
         let elapsed = now.elapsed();
-        println!("Part 2 took {:.5?} on average", elapsed / N);
+        println!("{} took {:.5?} on average", name, elapsed / N);
 
-        let mean1 = durations1
-            .iter()
-            .map(|f| f.as_secs_f64() * 1000_f64)
-            .fold(0f64, |x, y| x + y)
-            / (durations1.len() as f64);
-        let var1 = durations1
-            .iter()
-            .map(|f| f.as_secs_f64() * 1000_f64)
-            .fold(0f64, |x, y| x + (mean1 - y).powi(2))
-            / (durations1.len() as f64);
-        let mean2 = durations2
+        let mean = durations
             .iter()
             .map(|f| f.as_secs_f64() * 1000_f64)
             .fold(0f64, |x, y| x + y)
-            / (durations2.len() as f64);
-        let var2 = durations2
+            / (durations.len() as f64);
+        let var = durations
             .iter()
             .map(|f| f.as_secs_f64() * 1000_f64)
-            .fold(0f64, |x, y| x + (mean2 - y).powi(2))
-            / (durations2.len() as f64);
+            .fold(0f64, |x, y| x + (mean - y).powi(2))
+            / (durations.len() as f64);
+
+        (answer, Some((mean, var)))
+    }
+
+    pub fn benchmark(&self, input: &str) -> BenchmarkResult {
+        let (answer1, timing1) = self.time_part(Self::part_one, "Part 1", input);
+        let (answer2, timing2) = self.time_part(Self::part_two, "Part 2", input);
 
         BenchmarkResult {
             problem: 0,
-            mean_millis_pt1: mean1,
-            var_millis_squared_pt1: var1,
-            mean_millis_pt2: mean2,
-            var_millis_squared_pt2: var2,
+            answer_pt1: answer1.summary(),
+            mean_millis_pt1: timing1.map(|t| t.0),
+            var_millis_squared_pt1: timing1.map(|t| t.1),
+            answer_pt2: answer2.summary(),
+            mean_millis_pt2: timing2.map(|t| t.0),
+            var_millis_squared_pt2: timing2.map(|t| t.1),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct OnlyPartOne;
+
+    impl Problem for OnlyPartOne {
+        fn part_one(&self, input: &str) -> Answer {
+            input.len().into()
+        }
+
+        fn part_two(&self, _input: &str) -> Answer {
+            Answer::NotApplicable
+        }
+    }
+
+    #[test]
+    fn not_applicable_parts_are_not_timed() {
+        let p: Box<dyn Problem> = Box::new(OnlyPartOne);
+        let br = p.benchmark("abc");
+        assert_eq!(br.answer_pt1, "3");
+        assert!(br.mean_millis_pt1.is_some());
+        assert_eq!(br.answer_pt2, "n/a");
+        assert_eq!(br.mean_millis_pt2, None);
+        assert_eq!(br.var_millis_squared_pt2, None);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Answer::from(-12i64).to_string(), "-12");
+        assert_eq!(Answer::Text("HZLEHJRK".to_string()).to_string(), "HZLEHJRK");
+        let art = Answer::Art("#..\n.#.\n".to_string());
+        assert_eq!(art.to_string(), "#..\n.#.");
+        assert_eq!(art.summary(), "(2 lines of art)");
+        assert_eq!(Answer::NotApplicable.to_string(), "Not applicable");
+        assert_eq!(
+            Answer::Unsolved("no board wins".to_string()).to_string(),
+            "Unsolved: no board wins"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use num::{BigUint, One, Zero};
+
+/// A linear map between count vectors, such as one day of lanternfish
+/// timers or one step of polymer pairs. Cell (to, from) says how many
+/// counts `to` gains from every count in `from`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Matrix {
+    size: usize,
+    cells: Vec<BigUint>,
+}
+
+impl Matrix {
+    pub fn new(size: usize) -> Self {
+        Matrix {
+            size,
+            cells: vec![BigUint::zero(); size * size],
+        }
+    }
+
+    pub fn identity(size: usize) -> Self {
+        let mut m = Self::new(size);
+        for i in 0..size {
+            m.cells[i * size + i] = BigUint::one();
+        }
+        m
+    }
+
+    // Each count in `from` also adds `times` counts to `to`.
+    pub fn add(&mut self, to: usize, from: usize, times: u32) {
+        self.cells[to * self.size + from] += times;
+    }
+
+    fn reduce(n: BigUint, modulus: Option<&BigUint>) -> BigUint {
+        match modulus {
+            Some(m) => n % m,
+            None => n,
+        }
+    }
+
+    fn mul(&self, other: &Matrix, modulus: Option<&BigUint>) -> Matrix {
+        let n = self.size;
+        let mut r = Matrix::new(n);
+        for i in 0..n {
+            for k in 0..n {
+                let a = &self.cells[i * n + k];
+                if a.is_zero() {
+                    continue;
+                }
+                for j in 0..n {
+                    let b = &other.cells[k * n + j];
+                    if !b.is_zero() {
+                        r.cells[i * n + j] += a * b;
+                    }
+                }
+            }
+            for j in 0..n {
+                let c = std::mem::take(&mut r.cells[i * n + j]);
+                r.cells[i * n + j] = Self::reduce(c, modulus);
+            }
+        }
+        r
+    }
+
+    /// The map applied `e` times, by repeated squaring. Every product is
+    /// reduced by the modulus if there is one.
+    pub fn pow(&self, mut e: u64, modulus: Option<&BigUint>) -> Matrix {
+        let mut result = Matrix::identity(self.size);
+        let mut base = self.clone();
+        while e > 0 {
+            if e & 1 == 1 {
+                result = result.mul(&base, modulus);
+            }
+            e >>= 1;
+            if e > 0 {
+                base = base.mul(&base, modulus);
+            }
+        }
+        result
+    }
+
+    pub fn apply(&self, v: &[BigUint], modulus: Option<&BigUint>) -> Vec<BigUint> {
+        (0..self.size)
+            .map(|i| {
+                let row = &self.cells[i * self.size..(i + 1) * self.size];
+                let sum = row.iter().zip(v).map(|(a, b)| a * b).sum();
+                Self::reduce(sum, modulus)
+            })
+            .collect()
+    }
+}
+
+/// The counts after `steps` applications of `map` to `initial`, exact or
+/// modulo `modulus`. Exact counts grow exponentially with the steps, so
+/// very many steps need a modulus.
+pub fn advance(
+    map: &Matrix,
+    initial: &[BigUint],
+    steps: u64,
+    modulus: Option<&BigUint>,
+) -> Vec<BigUint> {
+    map.pow(steps, modulus).apply(initial, modulus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fibonacci() -> Matrix {
+        let mut m = Matrix::new(2);
+        m.add(0, 0, 1);
+        m.add(0, 1, 1);
+        m.add(1, 0, 1);
+        m
+    }
+
+    #[test]
+    fn powers_match_stepping() {
+        let m = fibonacci();
+        let mut v = vec![BigUint::one(), BigUint::zero()];
+        for steps in 0..50 {
+            assert_eq!(
+                advance(&m, &[BigUint::one(), BigUint::zero()], steps, None),
+                v
+            );
+            v = m.apply(&v, None);
+        }
+        // F(301), past any primitive integer.
+        let f = &advance(&m, &[BigUint::one(), BigUint::zero()], 300, None)[0];
+        assert_eq!(
+            f.to_string(),
+            "359579325206583560961765665172189099052367214309267232255589801"
+        );
+    }
+
+    #[test]
+    fn modular_counts() {
+        let m = fibonacci();
+        let modulus = BigUint::from(1_000_000_007u64);
+        let start = [BigUint::one(), BigUint::zero()];
+        let exact = advance(&m, &start, 1000, None);
+        let reduced = advance(&m, &start, 1000, Some(&modulus));
+        assert_eq!(reduced[0], &exact[0] % &modulus);
+        // Pisano period of 10 is 60, so 10^18 steps match 10^18 mod 60.
+        let ten = BigUint::from(10u32);
+        assert_eq!(
+            advance(&m, &start, 10u64.pow(18), Some(&ten)),
+            advance(&m, &start, 10u64.pow(18) % 60, Some(&ten))
+        );
+    }
+}
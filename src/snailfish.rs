@@ -0,0 +1,94 @@
+pub mod flat;
+pub mod tree;
+
+use std::fmt;
+use std::iter::Sum;
+use std::ops::Add;
+use std::str::FromStr;
+
+/// Operations shared by the snailfish number representations. Addition
+/// always returns a reduced number.
+pub trait Snailfish:
+    FromStr<Err = ParseSnailfishError> + fmt::Display + Clone + Add<Output = Self> + Sum
+{
+    fn magnitude(&self) -> u64;
+
+    /// The pair of both numbers, before any reduction.
+    fn pair(self, other: Self) -> Self;
+
+    /// Applies the first reduction action that applies, if any, and tells
+    /// which one it was and the index of the leftmost regular number it
+    /// changed.
+    fn step(&mut self) -> Option<(ActionKind, usize)>;
+
+    /// Explodes and splits in place until neither applies.
+    fn reduce(&mut self) {
+        while self.step().is_some() {}
+    }
+
+    /// Same as `reduce`, recording every action taken.
+    fn reduce_traced(&mut self) -> Vec<Action> {
+        let mut trace = vec![];
+        loop {
+            let before = self.to_string();
+            match self.step() {
+                Some((kind, position)) => trace.push(Action {
+                    kind,
+                    position,
+                    before,
+                    after: self.to_string(),
+                }),
+                None => return trace,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionKind {
+    Explode,
+    Split,
+}
+
+impl fmt::Display for ActionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionKind::Explode => write!(f, "explode"),
+            ActionKind::Split => write!(f, "split"),
+        }
+    }
+}
+
+/// One reduction action, with the number before and after it in bracket
+/// notation. `position` counts the regular numbers on the left.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Action {
+    pub kind: ActionKind,
+    pub position: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Tree,
+    Flat,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseSnailfishError {
+    pub position: usize,
+    pub expected: &'static str,
+}
+
+impl fmt::Display for ParseSnailfishError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Expected {} at position {}",
+            self.expected, self.position
+        )
+    }
+}
+
+impl std::error::Error for ParseSnailfishError {}
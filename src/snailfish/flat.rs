@@ -0,0 +1,162 @@
+use super::tree::Number;
+use super::{ActionKind, ParseSnailfishError, Snailfish};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::Add;
+use std::str::FromStr;
+
+/// Regular numbers from left to right, each with the number of pairs
+/// around it minus one. Exploding and splitting only shift a vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VecTree {
+    vals: Vec<u32>,
+    depths: Vec<u32>,
+}
+
+impl VecTree {
+    fn flatten(&mut self, n: &Number, depth: u32) {
+        match n {
+            Number::Regular(v) => {
+                self.vals.push(*v);
+                self.depths.push(depth - 1);
+            }
+            Number::Pair(l, r) => {
+                self.flatten(l, depth + 1);
+                self.flatten(r, depth + 1);
+            }
+        }
+    }
+
+    fn fmt_from(&self, f: &mut fmt::Formatter, i: &mut usize, depth: u32) -> fmt::Result {
+        if self.depths[*i] + 1 == depth {
+            *i += 1;
+            return write!(f, "{}", self.vals[*i - 1]);
+        }
+        write!(f, "[")?;
+        self.fmt_from(f, i, depth + 1)?;
+        write!(f, ",")?;
+        self.fmt_from(f, i, depth + 1)?;
+        write!(f, "]")
+    }
+
+    fn try_explode(&mut self) -> Option<usize> {
+        for i in 0..self.depths.len() - 1 {
+            let depth = self.depths[i];
+            if depth < 4 || self.depths[i + 1] != depth {
+                continue;
+            }
+
+            if i != 0 {
+                self.vals[i - 1] += self.vals[i];
+            }
+
+            if i + 2 < self.vals.len() {
+                self.vals[i + 2] += self.vals[i + 1];
+            }
+
+            self.vals[i] = 0;
+            self.depths[i] -= 1;
+            self.vals.remove(i + 1);
+            self.depths.remove(i + 1);
+
+            return Some(i);
+        }
+
+        None
+    }
+
+    fn try_split(&mut self) -> Option<usize> {
+        for i in 0..self.vals.len() {
+            let v = self.vals[i];
+            if v < 10 {
+                continue;
+            }
+
+            self.vals[i] = v / 2;
+            self.depths[i] += 1;
+            self.vals.insert(i + 1, v.div_ceil(2));
+            self.depths.insert(i + 1, self.depths[i]);
+
+            return Some(i);
+        }
+
+        None
+    }
+}
+
+impl FromStr for VecTree {
+    type Err = ParseSnailfishError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut t = VecTree {
+            vals: Vec::new(),
+            depths: Vec::new(),
+        };
+        t.flatten(&s.parse()?, 0);
+        Ok(t)
+    }
+}
+
+impl fmt::Display for VecTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_from(f, &mut 0, 0)
+    }
+}
+
+impl Snailfish for VecTree {
+    fn magnitude(&self) -> u64 {
+        let mut vals = self.vals.iter().map(|&v| v as u64).collect::<Vec<_>>();
+        let mut depths = self.depths.clone();
+
+        while vals.len() > 1 {
+            for i in 0..depths.len() - 1 {
+                if depths[i] == depths[i + 1] {
+                    vals[i] = 3 * vals[i] + 2 * vals[i + 1];
+                    vals.remove(i + 1);
+                    depths.remove(i + 1);
+
+                    if depths[i] > 0 {
+                        depths[i] -= 1;
+                    }
+
+                    break;
+                }
+            }
+        }
+
+        vals[0]
+    }
+
+    fn pair(mut self, other: Self) -> Self {
+        self.vals.extend(other.vals);
+        self.depths.extend(other.depths);
+        for d in self.depths.iter_mut() {
+            *d += 1;
+        }
+        self
+    }
+
+    fn step(&mut self) -> Option<(ActionKind, usize)> {
+        if let Some(i) = self.try_explode() {
+            return Some((ActionKind::Explode, i));
+        }
+        self.try_split().map(|i| (ActionKind::Split, i))
+    }
+}
+
+impl Add for VecTree {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut sum = self.pair(other);
+        sum.reduce();
+        sum
+    }
+}
+
+impl Sum for VecTree {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|acc, n| acc + n)
+            .expect("cannot sum zero snailfish numbers")
+    }
+}
@@ -0,0 +1,227 @@
+use super::{ActionKind, ParseSnailfishError, Snailfish};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::Add;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Number {
+    Regular(u32),
+    Pair(Box<Number>, Box<Number>),
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, expected: &'static str) -> ParseSnailfishError {
+        ParseSnailfishError {
+            position: self.pos,
+            expected,
+        }
+    }
+
+    fn expect(&mut self, c: u8, expected: &'static str) -> Result<(), ParseSnailfishError> {
+        if self.s.get(self.pos) != Some(&c) {
+            return Err(self.error(expected));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn element(&mut self) -> Result<Number, ParseSnailfishError> {
+        match self.s.get(self.pos) {
+            Some(b'[') => self.pair(),
+            Some(c) if c.is_ascii_digit() => self.regular(),
+            _ => Err(self.error("'[' or a digit")),
+        }
+    }
+
+    fn regular(&mut self) -> Result<Number, ParseSnailfishError> {
+        let start = self.pos;
+        let mut n = 0u32;
+        while let Some(c) = self.s.get(self.pos).filter(|c| c.is_ascii_digit()) {
+            n = n
+                .checked_mul(10)
+                .and_then(|n| n.checked_add((c - b'0') as u32))
+                .ok_or(ParseSnailfishError {
+                    position: start,
+                    expected: "a smaller number",
+                })?;
+            self.pos += 1;
+        }
+        Ok(Number::Regular(n))
+    }
+
+    fn pair(&mut self) -> Result<Number, ParseSnailfishError> {
+        self.expect(b'[', "'['")?;
+        let left = self.element()?;
+        self.expect(b',', "','")?;
+        let right = self.element()?;
+        self.expect(b']', "']'")?;
+        Ok(Number::Pair(Box::new(left), Box::new(right)))
+    }
+}
+
+impl FromStr for Number {
+    type Err = ParseSnailfishError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            s: s.as_bytes(),
+            pos: 0,
+        };
+        let n = parser.pair()?;
+        if parser.pos != s.len() {
+            return Err(parser.error("the end of the number"));
+        }
+        Ok(n)
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Number::Regular(n) => write!(f, "{}", n),
+            Number::Pair(l, r) => write!(f, "[{},{}]", l, r),
+        }
+    }
+}
+
+impl Number {
+    fn add_leftmost(&mut self, v: u32) {
+        match self {
+            Number::Regular(n) => *n += v,
+            Number::Pair(l, _) => l.add_leftmost(v),
+        }
+    }
+
+    fn add_rightmost(&mut self, v: u32) {
+        match self {
+            Number::Regular(n) => *n += v,
+            Number::Pair(_, r) => r.add_rightmost(v),
+        }
+    }
+
+    // Explodes the leftmost pair of regular numbers nested inside four
+    // pairs, returning the values still to be added on its left and right.
+    // `index` counts the regular numbers passed on the way.
+    fn explode(&mut self, depth: usize, index: &mut usize) -> Option<(u32, u32)> {
+        let Number::Pair(l, r) = self else {
+            *index += 1;
+            return None;
+        };
+        if let (4.., Number::Regular(a), Number::Regular(b)) = (depth, &**l, &**r) {
+            let carry = (*a, *b);
+            *self = Number::Regular(0);
+            return Some(carry);
+        }
+        if let Some((a, b)) = l.explode(depth + 1, index) {
+            r.add_leftmost(b);
+            return Some((a, 0));
+        }
+        if let Some((a, b)) = r.explode(depth + 1, index) {
+            l.add_rightmost(a);
+            return Some((0, b));
+        }
+        None
+    }
+
+    fn split(&mut self, index: &mut usize) -> bool {
+        match self {
+            Number::Regular(n) if *n >= 10 => {
+                *self = Number::Pair(
+                    Box::new(Number::Regular(*n / 2)),
+                    Box::new(Number::Regular(n.div_ceil(2))),
+                );
+                true
+            }
+            Number::Regular(_) => {
+                *index += 1;
+                false
+            }
+            Number::Pair(l, r) => l.split(index) || r.split(index),
+        }
+    }
+}
+
+impl Snailfish for Number {
+    fn magnitude(&self) -> u64 {
+        match self {
+            Number::Regular(n) => *n as u64,
+            Number::Pair(l, r) => 3 * l.magnitude() + 2 * r.magnitude(),
+        }
+    }
+
+    fn pair(self, other: Self) -> Self {
+        Number::Pair(Box::new(self), Box::new(other))
+    }
+
+    fn step(&mut self) -> Option<(ActionKind, usize)> {
+        let mut index = 0;
+        if self.explode(0, &mut index).is_some() {
+            return Some((ActionKind::Explode, index));
+        }
+        index = 0;
+        if self.split(&mut index) {
+            return Some((ActionKind::Split, index));
+        }
+        None
+    }
+}
+
+impl Add for Number {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let mut sum = self.pair(other);
+        sum.reduce();
+        sum
+    }
+}
+
+impl Sum for Number {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|acc, n| acc + n)
+            .expect("cannot sum zero snailfish numbers")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        let n: Number = "[[1,23],[[456,7],8]]".parse().unwrap();
+        assert_eq!(n.to_string(), "[[1,23],[[456,7],8]]");
+        assert_eq!(
+            "[1,2".parse::<Number>(),
+            Err(ParseSnailfishError {
+                position: 4,
+                expected: "']'"
+            })
+        );
+        assert_eq!(
+            "[1,[x,2]]".parse::<Number>(),
+            Err(ParseSnailfishError {
+                position: 4,
+                expected: "'[' or a digit"
+            })
+        );
+        assert_eq!("[1,2]]".parse::<Number>().unwrap_err().position, 5);
+        assert_eq!("7".parse::<Number>().unwrap_err().position, 0);
+    }
+
+    #[test]
+    fn reduce_in_place() {
+        let mut n: Number = "[[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]".parse().unwrap();
+        n.reduce();
+        assert_eq!(n.to_string(), "[[[[0,7],4],[[7,8],[6,0]]],[8,1]]");
+        let mut n: Number = "[[[[[[1,2],3],4],5],6],7]".parse().unwrap();
+        n.reduce();
+        assert_eq!(n.to_string(), "[[[[0,9],5],6],7]");
+    }
+}